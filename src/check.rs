@@ -0,0 +1,132 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+};
+
+use tracing_subscriber::EnvFilter;
+
+use crate::{commands::lexicon::word_of_the_day, error::Error, git, Config};
+
+/// Finds every problem of a configuration that can be found without touching the repositories.
+pub fn validate(config: &Config) -> Vec<Error> {
+    let mut errors = Vec::new();
+    let mut problem = |problem: String| errors.push(Error::Config(problem));
+    if config.lexicons.is_empty() {
+        problem("No lexicons present".to_string());
+    }
+    if config.lexicons.len() > 25 {
+        problem("Too many lexicons (max. 25)".to_string());
+    }
+    if config.repositories.contains_key("default") {
+        problem("The repository name \"default\" is reserved".to_string());
+    }
+    if let Err(err) = EnvFilter::try_new(&config.log.level) {
+        problem(format!("Invalid log level: {err}"));
+    }
+    let mut names = HashSet::new();
+    // the lexicon that first claimed a file by repository and path
+    let mut files = HashMap::new();
+    for lexicon in &config.lexicons {
+        let name = &lexicon.name;
+        if !names.insert(name.as_str()) {
+            problem(format!("There are multiple lexicons named {name}"));
+        }
+        let repository = lexicon.repository.as_deref().unwrap_or("default");
+        if repository != "default" && !config.repositories.contains_key(repository) {
+            problem(format!(
+                "Lexicon {name} uses unknown repository {repository}"
+            ));
+        }
+        if let Some(word_of_the_day) = &lexicon.word_of_the_day {
+            if word_of_the_day::parse_time(&word_of_the_day.time).is_none() {
+                problem(format!(
+                    "Lexicon {name} has an invalid word of the day time {}",
+                    word_of_the_day.time
+                ));
+            }
+        }
+        let outputs = lexicon.outputs.iter().map(|output| &output.file);
+        for file in [&lexicon.file, &lexicon.target_file]
+            .into_iter()
+            .chain(outputs)
+        {
+            let path = lexicon.directory.join(file);
+            let Some(path) = contained(&path) else {
+                problem(format!(
+                    "Lexicon {name} has the file {} outside of its repository",
+                    path.display()
+                ));
+                continue;
+            };
+            if let Some(other) = files.insert((repository, path.clone()), name) {
+                let path = path.display();
+                if other == name {
+                    problem(format!("Lexicon {name} uses the file {path} twice"));
+                } else {
+                    problem(format!("Lexicons {other} and {name} share the file {path}"));
+                }
+            }
+        }
+    }
+    errors
+}
+
+/// Validates a configuration and checks whether the remotes can be reached.
+pub fn check(config: &Config) -> Vec<Error> {
+    let mut errors = validate(config);
+    let repositories = [("default", &config.git)].into_iter().chain(
+        config
+            .repositories
+            .iter()
+            .map(|(name, repository)| (name.as_str(), repository)),
+    );
+    for (name, repository) in repositories {
+        if let Err(err) = git::probe(repository) {
+            errors.push(Error::Git {
+                repository: name.to_string(),
+                reason: format!("The remote {} is not reachable: {err}", repository.url),
+            });
+        }
+    }
+    errors
+}
+
+/// Resolves `.` and `..` without touching the file system, `None` if the path leaves the
+/// directory it is relative to.
+fn contained(path: &Path) -> Option<PathBuf> {
+    let mut contained = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => contained.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !contained.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(contained)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_contained_paths() {
+        assert_eq!(
+            contained(Path::new("lexicons/./old/../words.ron")),
+            Some(PathBuf::from("lexicons/words.ron"))
+        );
+        assert_eq!(contained(Path::new("a/..")), Some(PathBuf::new()));
+    }
+
+    #[test]
+    fn rejects_escaping_paths() {
+        assert_eq!(contained(Path::new("../words.ron")), None);
+        assert_eq!(contained(Path::new("lexicons/../../words.ron")), None);
+        assert_eq!(contained(Path::new("/etc/passwd")), None);
+    }
+}
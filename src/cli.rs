@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: sqyyy-bot [OPTIONS] [COMMAND]
+
+Commands:
+  sync              Pull every repository, push pending commits and exit
+  export <LEXICON>  Print the rendered target file of a lexicon and exit
+
+Options:
+  -c, --config <PATH>    Config file [default: .config.toml]
+      --check-config     Report every problem of the config without connecting to Discord
+      --dry-run          Check the config and remotes without changing them or connecting
+      --log-level <LOG>  Filter directives like `debug`, takes precedence over RUST_LOG
+  -h, --help             Print this help
+
+Every config field can be overridden by an environment variable like SQYYY_BOT__GIT__PASSWORD.";
+
+pub struct Args {
+    pub config: PathBuf,
+    pub check_config: bool,
+    pub dry_run: bool,
+    pub log_level: Option<String>,
+    pub command: Option<OneShot>,
+    pub help: bool,
+}
+
+/// A command that runs instead of the bot.
+pub enum OneShot {
+    Sync,
+    Export { lexicon: String },
+}
+
+/// Parses the arguments without the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        config: PathBuf::from(".config.toml"),
+        check_config: false,
+        dry_run: false,
+        log_level: None,
+        command: None,
+        help: false,
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{name} needs a value"));
+        match arg.as_str() {
+            "-c" | "--config" => parsed.config = PathBuf::from(value(&arg)?),
+            "--check-config" => parsed.check_config = true,
+            "--dry-run" => parsed.dry_run = true,
+            "--log-level" => parsed.log_level = Some(value(&arg)?),
+            "-h" | "--help" => parsed.help = true,
+            _ if parsed.command.is_some() => return Err(format!("Unexpected argument {arg}")),
+            "sync" => parsed.command = Some(OneShot::Sync),
+            "export" => {
+                parsed.command = Some(OneShot::Export {
+                    lexicon: value(&arg)?,
+                })
+            }
+            _ => return Err(format!("Unknown argument {arg}")),
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults() {
+        let parsed = args(&[]).unwrap();
+        assert_eq!(parsed.config, PathBuf::from(".config.toml"));
+        assert!(!parsed.check_config && !parsed.dry_run && !parsed.help);
+        assert!(parsed.log_level.is_none() && parsed.command.is_none());
+    }
+
+    #[test]
+    fn options_and_command() {
+        let parsed = args(&["-c", "bot.toml", "--log-level", "debug", "export", "words"]).unwrap();
+        assert_eq!(parsed.config, PathBuf::from("bot.toml"));
+        assert_eq!(parsed.log_level.as_deref(), Some("debug"));
+        assert!(matches!(parsed.command, Some(OneShot::Export { lexicon }) if lexicon == "words"));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(args(&["--config"]).is_err());
+        assert!(args(&["export"]).is_err());
+        assert!(args(&["sync", "sync"]).is_err());
+        assert!(args(&["--unknown"]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::CollationConfig;
+
+/// The entries of a lexicon listed under one letter.
+pub struct Group<'a> {
+    pub letter: String,
+    pub entries: Vec<(&'a str, &'a str)>,
+}
+
+/// Sort key of a text, the folded letters with their rank in the alphabet come first so
+/// diacritics and case only decide between otherwise equal texts.
+type Key = (Vec<(usize, String)>, String);
+
+/// Removes diacritics and case.
+fn fold(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Splits a text into the letters of the alphabet, preferring the longest match so digraphs
+/// are kept together.
+fn letters(config: &CollationConfig, text: &str) -> Vec<(Option<usize>, String)> {
+    let text = text.nfc().collect::<String>().to_uppercase();
+    let mut rest = text.as_str();
+    let mut letters = Vec::new();
+    while let Some(c) = rest.chars().next() {
+        let letter = config
+            .alphabet
+            .iter()
+            .map(|letter| letter.to_uppercase())
+            .enumerate()
+            .filter(|(_, letter)| !letter.is_empty() && rest.starts_with(letter.as_str()))
+            .max_by_key(|(_, letter)| letter.len());
+        match letter {
+            Some((rank, letter)) => {
+                rest = &rest[letter.len()..];
+                letters.push((Some(rank), letter));
+            }
+            None => {
+                rest = &rest[c.len_utf8()..];
+                letters.push((None, c.to_string()));
+            }
+        }
+    }
+    letters
+}
+
+fn rank(config: &CollationConfig, letter: &str) -> usize {
+    config
+        .alphabet
+        .iter()
+        .position(|other| fold(other) == letter)
+        .unwrap_or(config.alphabet.len())
+}
+
+fn key(config: &CollationConfig, text: &str) -> Key {
+    let primary = letters(config, text)
+        .into_iter()
+        .map(|(rank_in_alphabet, letter)| {
+            let folded = fold(&letter);
+            let rank = rank_in_alphabet.unwrap_or_else(|| rank(config, &folded));
+            (rank, folded)
+        })
+        .collect();
+    (primary, text.to_string())
+}
+
+/// The letter a word is listed under.
+pub fn letter(config: &CollationConfig, word: &str) -> String {
+    let Some((rank, letter)) = letters(config, word).into_iter().next() else {
+        return String::new();
+    };
+    if rank.is_none() && config.fold_diacritics {
+        return fold(&letter);
+    }
+    letter
+}
+
+/// Groups the entries of a lexicon by letter, both in dictionary order.
+pub fn group<'a>(
+    config: &CollationConfig,
+    words: &'a BTreeMap<char, BTreeMap<String, String>>,
+) -> Vec<Group<'a>> {
+    let mut entries = words
+        .values()
+        .flatten()
+        .map(|(word, description)| (letter(config, word), word.as_str(), description.as_str()))
+        .collect::<Vec<_>>();
+    entries.sort_by_cached_key(|(letter, word, _)| (key(config, letter), key(config, word)));
+    let mut groups = Vec::<Group>::new();
+    for (letter, word, description) in entries {
+        match groups.last_mut() {
+            Some(group) if group.letter == letter => group.entries.push((word, description)),
+            _ => groups.push(Group {
+                letter,
+                entries: vec![(word, description)],
+            }),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(fold_diacritics: bool, alphabet: &[&str]) -> CollationConfig {
+        CollationConfig {
+            fold_diacritics,
+            alphabet: alphabet.iter().map(|letter| letter.to_string()).collect(),
+        }
+    }
+
+    /// The letters and words of the groups of the given words.
+    fn grouped(config: &CollationConfig, words: &[&str]) -> Vec<(String, Vec<String>)> {
+        let mut buckets = BTreeMap::<char, BTreeMap<String, String>>::new();
+        for word in words {
+            let bucket = word.chars().next().unwrap();
+            let entries = buckets.entry(bucket).or_default();
+            entries.insert(word.to_string(), String::new());
+        }
+        group(config, &buckets)
+            .into_iter()
+            .map(|group| {
+                let words = group.entries.iter().map(|(word, _)| word.to_string());
+                (group.letter, words.collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_by_letter_ignoring_case() {
+        let groups = grouped(&config(false, &[]), &["banana", "Apple", "avocado"]);
+        assert_eq!(
+            groups,
+            [
+                (
+                    "A".to_string(),
+                    vec!["Apple".to_string(), "avocado".to_string()]
+                ),
+                ("B".to_string(), vec!["banana".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn folds_diacritics() {
+        assert_eq!(letter(&config(false, &[]), "äpfel"), "Ä");
+        assert_eq!(letter(&config(true, &[]), "äpfel"), "A");
+        let groups = grouped(&config(true, &[]), &["Birne", "Äpfel", "Apfel"]);
+        assert_eq!(
+            groups,
+            [
+                (
+                    "A".to_string(),
+                    vec!["Apfel".to_string(), "Äpfel".to_string()]
+                ),
+                ("B".to_string(), vec!["Birne".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_digraphs_together() {
+        let config = config(false, &["A", "B", "C", "CH", "D"]);
+        assert_eq!(letter(&config, "chili"), "CH");
+        let groups = grouped(&config, &["dach", "chili", "cola"]);
+        let letters = groups
+            .iter()
+            .map(|(letter, _)| letter.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(letters, ["C", "CH", "D"]);
+    }
+}
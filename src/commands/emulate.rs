@@ -1,15 +1,21 @@
 use emulator::emulator::Emulator;
 use serenity::{
+    async_trait,
     builder::CreateApplicationCommand,
+    client::Context,
     model::prelude::{
-        component::{ActionRowComponent, InputTextStyle},
-        interaction::{application_command::CommandDataOption, modal::ModalSubmitInteraction},
+        component::InputTextStyle,
+        interaction::{
+            application_command::{ApplicationCommandInteraction, CommandDataOption},
+            modal::ModalSubmitInteraction,
+        },
     },
 };
 
+use super::{text_inputs, SlashCommand};
 use crate::{
     parser::{parse, tokenize},
-    Modal, Response,
+    Handler, Modal, Response,
 };
 
 pub fn run(_options: &[CommandDataOption]) -> Response {
@@ -29,23 +35,16 @@ pub fn run(_options: &[CommandDataOption]) -> Response {
                 })
             });
         },
-        Modal::Emulate,
+        Modal::new("emulate", "code", ""),
     )
 }
 
 pub async fn handle_modal(submission: &mut ModalSubmitInteraction) -> Response {
-    let ActionRowComponent::InputText(input) = submission
-        .data
-        .components
-        .drain(..)
-        .next()
-        .unwrap()
-        .components
-        .drain(..)
-        .next()
-        .unwrap() else {unimplemented!()};
+    let Ok([input]) = <[String; 1]>::try_from(text_inputs(submission)) else {
+        return Response::invalid_command();
+    };
     let mut response = String::new();
-    for (i, statement) in input.value.split('\n').enumerate() {
+    for (i, statement) in input.split('\n').enumerate() {
         let tokens = tokenize(statement);
         if let Err(err) = tokens {
             return Response::failure(format!("[{i}] Parsing error"), err.to_string());
@@ -83,3 +82,38 @@ pub async fn handle_modal(submission: &mut ModalSubmitInteraction) -> Response {
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
     command.name("emulate").description("Emulate a circuit")
 }
+
+pub struct EmulateCommand;
+
+#[async_trait]
+impl SlashCommand for EmulateCommand {
+    fn name(&self) -> &'static str {
+        "emulate"
+    }
+
+    fn register<'a>(
+        &self,
+        _handler: &Handler,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(
+        &self,
+        _handler: &Handler,
+        _ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Response {
+        run(&command.data.options)
+    }
+
+    async fn modal(
+        &self,
+        _handler: &Handler,
+        _modal: Modal,
+        submission: &mut ModalSubmitInteraction,
+    ) -> Response {
+        handle_modal(submission).await
+    }
+}
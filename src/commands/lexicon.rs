@@ -1,26 +1,55 @@
+pub mod admin;
+pub mod quiz;
+pub mod stats;
+pub mod word_of_the_day;
+
 use std::{
     collections::BTreeMap,
     fs::{self, write, File},
     io::Write,
-    path::PathBuf,
-    process::exit,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, RwLock, TryLockError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use rand::seq::IteratorRandom;
 use serde::{Deserialize, Serialize};
 use serenity::{
-    builder::{CreateApplicationCommand, CreateComponents},
+    async_trait,
+    builder::{CreateApplicationCommand, CreateApplicationCommandOption, CreateComponents},
+    client::Context,
+    http::Http,
     json::Value,
     model::{
+        guild::Member,
+        id::{GuildId, UserId},
         prelude::{
-            command::CommandOptionType,
-            component::{ActionRowComponent, InputTextStyle},
-            interaction::{application_command::CommandDataOption, modal::ModalSubmitInteraction},
+            command::{Command, CommandOptionType},
+            component::{ButtonStyle, InputTextStyle},
+            interaction::{
+                application_command::{ApplicationCommandInteraction, CommandDataOption},
+                autocomplete::AutocompleteInteraction,
+                message_component::MessageComponentInteraction,
+                modal::ModalSubmitInteraction,
+            },
         },
         user::User,
     },
 };
 
-use crate::{git, Config, Handler, LexiconConfig, Modal, Response};
+use tracing::{error, info, warn};
+
+use super::{text_inputs, SlashCommand};
+use crate::{
+    collation,
+    error::Error,
+    git::{self, Repository},
+    render,
+    suggestions::{Suggestion, SuggestionKind, Suggestions},
+    template, validation, AccessConfig, AccessRule, Button, CollationConfig, CommitAuthor, Handler,
+    LexiconConfig, Modal, OutputConfig, RenderConfig, Response, Select, ValidationConfig,
+    WordOfTheDayConfig,
+};
 
 #[derive(Default, Deserialize, Serialize)]
 pub struct Lexicon {
@@ -28,48 +57,355 @@ pub struct Lexicon {
     file: PathBuf,
     target_file: PathBuf,
     words: BTreeMap<char, BTreeMap<String, String>>,
+    #[serde(skip)]
+    modified: Option<SystemTime>,
+    /// Index of the repository the lexicon is stored in.
+    #[serde(skip)]
+    repository: usize,
+    /// Directory of the lexicon files relative to the repository root.
+    #[serde(skip)]
+    directory: PathBuf,
+    #[serde(skip)]
+    render: RenderConfig,
+    #[serde(skip)]
+    outputs: Vec<OutputConfig>,
+    #[serde(skip)]
+    collation: CollationConfig,
+    #[serde(skip)]
+    validation: ValidationConfig,
+    #[serde(skip)]
+    word_of_the_day: Option<WordOfTheDayConfig>,
+}
+
+/// A lexicon with the data it is looked up and authorized by, which can be read while an edit
+/// holds the lexicon.
+pub struct Slot {
+    /// The name of the lexicon, renamed together with it.
+    name: String,
+    /// Index of the repository the lexicon is stored in.
+    repository: usize,
+    /// Guilds the lexicon is restricted to, available everywhere if empty.
+    guilds: Vec<GuildId>,
+    access: AccessConfig,
+    lexicon: Mutex<Lexicon>,
+}
+
+impl Slot {
+    fn lock(&self) -> MutexGuard<'_, Lexicon> {
+        self.lexicon.lock().unwrap()
+    }
+
+    /// Locks the lexicon without waiting for an edit, which holds it while talking to git.
+    fn try_lock(&self) -> Result<MutexGuard<'_, Lexicon>, Response> {
+        match self.lexicon.try_lock() {
+            Err(TryLockError::WouldBlock) => Err(Response::failure(
+                "Lexicon busy",
+                "The lexicon is being edited, please try again in a moment.",
+            )
+            .ephemeral()),
+            lexicon => Ok(lexicon.unwrap()),
+        }
+    }
+
+    fn available_in(&self, guild_id: Option<GuildId>) -> bool {
+        self.guilds.is_empty()
+            || matches!(guild_id, Some(guild_id) if self.guilds.contains(&guild_id))
+    }
+
+    /// Determines the access level of a user, absent rules fall back to the level below.
+    fn access(&self, user: &User, member: Option<&Member>) -> Access {
+        let roles = member
+            .map(|member| member.roles.as_slice())
+            .unwrap_or_default();
+        let matches = |rule: &AccessRule| {
+            rule.users.contains(&user.id) || rule.roles.iter().any(|role| roles.contains(role))
+        };
+        let access = &self.access;
+        let read = access.read.as_ref().map(matches).unwrap_or(true);
+        let suggest = access.suggest.as_ref().map(matches).unwrap_or(read);
+        let edit = access.edit.as_ref().map(matches).unwrap_or(suggest);
+        let admin = access.admin.as_ref().map(matches).unwrap_or(false);
+        if admin {
+            Access::Admin
+        } else if edit {
+            Access::Edit
+        } else if suggest {
+            Access::Suggest
+        } else if read {
+            Access::Read
+        } else {
+            Access::None
+        }
+    }
+
+    /// Fails with a response if the user lacks the required access level.
+    fn require(
+        &self,
+        user: &User,
+        member: Option<&Member>,
+        required: Access,
+    ) -> Result<(), Response> {
+        if self.access(user, member) >= required {
+            return Ok(());
+        }
+        let text = match required {
+            Access::None | Access::Read => "You are not allowed to view this lexicon.",
+            Access::Suggest => "You are not allowed to suggest changes to this lexicon.",
+            Access::Edit => "You are not allowed to edit this lexicon.",
+            Access::Admin => "You are not allowed to administrate this lexicon.",
+        };
+        Err(Response::failure("Permission denied", text).ephemeral())
+    }
 }
 
-pub fn load(config: &Config, lexicon_config: LexiconConfig) -> Lexicon {
-    let mut path = config.git.path.clone();
-    path.push(&lexicon_config.file);
+/// What a user may do with a lexicon, every level includes the ones below it.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    None,
+    Read,
+    Suggest,
+    Edit,
+    Admin,
+}
+
+impl Lexicon {
+    fn repository<'a>(&self, repositories: &'a [Repository]) -> &'a Repository {
+        &repositories[self.repository]
+    }
+
+    fn relative_path(&self, file: &Path) -> PathBuf {
+        self.directory.join(file)
+    }
+
+    fn entry(&self, word: &str) -> Option<&String> {
+        self.words.get(&bucket(word)?)?.get(word)
+    }
+
+    /// Finds the word an existing entry is stored under, normalized or as typed.
+    ///
+    /// Entries from before the whitespace handling was configured may not be normalized.
+    fn lookup(&self, word: &str) -> String {
+        let normalized = validation::normalize(&self.validation, word);
+        if self.entry(&normalized).is_none() && self.entry(word).is_some() {
+            return word.to_string();
+        }
+        normalized
+    }
+
+    /// Renders the target file.
+    fn rendered(&self) -> Result<String, String> {
+        let groups = collation::group(&self.collation, &self.words);
+        render::render(&self.name, &groups, &self.render)
+    }
+}
+
+/// Loads a lexicon, creating an empty lexicon file if there is none yet.
+pub fn load(repositories: &[Repository], lexicon_config: LexiconConfig) -> Result<Slot, Error> {
+    let name = &lexicon_config.name;
+    // the config is validated before any lexicon is loaded, so the repository exists
+    let repository = lexicon_config
+        .repository
+        .as_ref()
+        .and_then(|repository| {
+            repositories
+                .iter()
+                .position(|candidate| &candidate.name == repository)
+        })
+        .unwrap_or_default();
+    let error = |reason: String| Error::Lexicon {
+        name: name.clone(),
+        reason,
+    };
+    let directory = repositories[repository]
+        .config
+        .path
+        .join(&lexicon_config.directory);
+    fs::create_dir_all(&directory)
+        .map_err(|err| error(format!("Could not create lexicon directory: {err}")))?;
+    let path = directory.join(&lexicon_config.file);
     if !path.exists() || !path.is_file() {
-        let file = File::create(&path);
-        if let Err(err) = file {
-            eprintln!("Could not create lexicon config: {err}");
-            exit(1);
-        }
-        let mut file = file.unwrap();
-        let lexicon = ron::to_string(&Lexicon::default());
-        if let Err(err) = lexicon {
-            eprintln!("Could not create lexicon config: {err}");
-            exit(1);
-        }
-        if let Err(err) = file.write_all(lexicon.unwrap().as_bytes()) {
-            eprintln!("Could not create lexicon config: {err}");
-            exit(1);
-        }
-    }
-    let lexicon = fs::read_to_string(&path);
-    if let Err(err) = lexicon {
-        eprintln!("Could not load lexicon config: {err}");
-        exit(1);
-    }
-    let words_str = lexicon.unwrap();
-    let words = ron::from_str(&words_str);
-    if let Err(err) = words {
-        eprintln!("Could not load lexicon config: {err}");
-        exit(1);
-    }
-    Lexicon {
-        name: lexicon_config.name,
+        let mut file = File::create(&path)
+            .map_err(|err| error(format!("Could not create lexicon file: {err}")))?;
+        let lexicon = ron::to_string(&Lexicon::default())
+            .map_err(|err| error(format!("Could not create lexicon file: {err}")))?;
+        file.write_all(lexicon.as_bytes())
+            .map_err(|err| error(format!("Could not create lexicon file: {err}")))?;
+    }
+    let words = read_words(&path).map_err(error)?;
+    let lexicon = Lexicon {
+        name: lexicon_config.name.clone(),
         file: lexicon_config.file,
         target_file: lexicon_config.target_file,
-        words: words.unwrap(),
+        words,
+        modified: modified(&path),
+        repository,
+        directory: lexicon_config.directory,
+        render: lexicon_config.render,
+        outputs: lexicon_config.outputs,
+        collation: lexicon_config.collation,
+        validation: lexicon_config.validation,
+        word_of_the_day: lexicon_config.word_of_the_day,
+    };
+    Ok(Slot {
+        name: lexicon_config.name,
+        repository,
+        guilds: lexicon_config.guilds,
+        access: lexicon_config.access,
+        lexicon: Mutex::new(lexicon),
+    })
+}
+
+fn read_words(path: &Path) -> Result<BTreeMap<char, BTreeMap<String, String>>, String> {
+    let words = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    ron::from_str(&words).map_err(|err| err.to_string())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Re-reads the lexicon file if it changed since it was last loaded or written.
+///
+/// Returns whether the lexicon was reloaded.
+fn reload(repository: &Repository, lexicon: &mut Lexicon) -> Result<bool, String> {
+    let path = repository
+        .config
+        .path
+        .join(lexicon.relative_path(&lexicon.file));
+    let modified = modified(&path);
+    if modified.is_some() && modified == lexicon.modified {
+        return Ok(false);
     }
+    // remember the new state even if parsing fails so a broken file is only reported once
+    lexicon.modified = modified;
+    lexicon.words = read_words(&path)?;
+    Ok(true)
 }
 
-pub fn run(handler: &Handler, user: &User, options: &[CommandDataOption]) -> Response {
+/// Pulls the repository and reloads every lexicon in it whose file changed upstream.
+pub fn sync(repositories: &[Repository], index: usize, lexicons: &[Slot]) {
+    let repository = &repositories[index];
+    // only the repository is held while talking to git, so lexicons stay readable meanwhile
+    let lock = repository.lock();
+    let outbox = git::outbox(&repository.config);
+    if !outbox.is_empty() {
+        let flushed = git::flush(&repository.config);
+        if flushed != git::Status::Success {
+            // pending changes are expected to wait while the remote is unreachable
+            if flushed == git::Status::TimedOut {
+                warn!(
+                    repository = %repository.name,
+                    pending = outbox.len(),
+                    "Pushing pending changes timed out"
+                );
+            } else if git::reachable(&repository.config) {
+                error!(
+                    repository = %repository.name,
+                    pending = outbox.len(),
+                    "Could not merge or push pending changes"
+                );
+            } else {
+                warn!(
+                    repository = %repository.name,
+                    pending = outbox.len(),
+                    "Could not push pending changes, the remote is unreachable"
+                );
+            }
+            repository.set_synced(false);
+            return;
+        }
+        info!(
+            repository = %repository.name,
+            pending = outbox.len(),
+            "Pushed pending changes"
+        );
+    } else if git::pull(&repository.config) != git::Status::Success {
+        error!(repository = %repository.name, "Could not pull git repository");
+        repository.set_synced(false);
+        return;
+    }
+    repository.set_synced(true);
+    drop(lock);
+    // an edit that ran since the pull remembers the modification time of its write, so its
+    // lexicon is not read again
+    for slot in lexicons.iter().filter(|slot| slot.repository == index) {
+        let mut lexicon = slot.lock();
+        match reload(repository, &mut lexicon) {
+            Ok(true) => info!(lexicon = %lexicon.name, "Reloaded lexicon"),
+            Ok(false) => {}
+            Err(err) => error!(lexicon = %lexicon.name, "Could not reload lexicon: {err}"),
+        }
+    }
+}
+
+/// The names and entry counts of the lexicons.
+pub fn sizes(lexicons: &[Slot]) -> Vec<(String, usize)> {
+    lexicons
+        .iter()
+        .map(|slot| {
+            let lexicon = slot.lock();
+            let entries = lexicon.words.values().map(BTreeMap::len).sum();
+            (lexicon.name.clone(), entries)
+        })
+        .collect()
+}
+
+/// Renders the target file of a lexicon regardless of the guilds it is restricted to.
+pub fn export(lexicons: &[Slot], name: &str) -> Result<String, String> {
+    let slot = lexicons
+        .iter()
+        .find(|slot| slot.name == name)
+        .ok_or_else(|| "The lexicon could not be found".to_string())?;
+    slot.lock().rendered()
+}
+
+/// Interval in seconds between attempts to push pending changes while syncing is disabled.
+const FLUSH_INTERVAL: u64 = 300;
+
+/// Periodically syncs the lexicons with a remote repository.
+///
+/// With syncing disabled, an offline tolerant repository still pushes its pending changes.
+pub async fn watch(
+    repositories: Arc<Vec<Repository>>,
+    repository: usize,
+    lexicons: Arc<RwLock<Vec<Slot>>>,
+) {
+    let config = &repositories[repository].config;
+    let flush_only = config.sync_interval == 0;
+    if flush_only && !config.offline_tolerant {
+        return;
+    }
+    let seconds = if flush_only {
+        FLUSH_INTERVAL
+    } else {
+        config.sync_interval
+    };
+    let mut interval = tokio::time::interval(Duration::from_secs(seconds));
+    // the first tick completes immediately
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if flush_only && git::outbox(&repositories[repository].config).is_empty() {
+            continue;
+        }
+        let repositories = repositories.clone();
+        let lexicons = lexicons.clone();
+        if let Err(err) = tokio::task::spawn_blocking(move || {
+            sync(&repositories, repository, &lexicons.read().unwrap())
+        })
+        .await
+        {
+            error!("Could not sync lexicons: {err}");
+        }
+    }
+}
+
+pub fn run(handler: &Handler, command: &ApplicationCommandInteraction) -> Response {
+    let user = &command.user;
+    let member = command.member.as_ref();
+    let guild_id = command.guild_id;
+    let lexicons = handler.lexicons.read().unwrap();
+    let options = &command.data.options;
     if options.len() != 1 {
         return Response::invalid_command();
     }
@@ -83,24 +419,21 @@ pub fn run(handler: &Handler, user: &User, options: &[CommandDataOption]) -> Res
             let Some(Value::String(lexicon_name)) = &options[0].value else {
                 return Response::invalid_command();
             };
-            let mut index = None;
-            for (i, lexicon_) in handler.lexicons.iter().enumerate() {
-                let guard = lexicon_.lock().unwrap();
-                if &guard.name == lexicon_name {
-                    index = Some(i);
-                    break;
-                }
-            }
+            let index = find(&lexicons, guild_id, lexicon_name);
             let Some(index) = index else {
                 return Response::failure("Add entry error", "The lexicon could not be found.");
             };
+            let slot = &lexicons[index];
+            if let Err(response) = slot.require(user, member, Access::Suggest) {
+                return response;
+            }
             Response::modal(
                 |handler, response| {
                     response
                         .title("Add lexicon entry")
                         .set_components(handler.lexicon_add_modal.clone());
                 },
-                Modal::LexiconAdd { index },
+                Modal::new("lexicon", "add", slot.name.clone()),
             )
         }
         "query" => {
@@ -111,28 +444,25 @@ pub fn run(handler: &Handler, user: &User, options: &[CommandDataOption]) -> Res
             let Some(Value::String(lexicon_name)) = &options[0].value else {
                 return Response::invalid_command();
             };
-            let mut lexicon = None;
-            for lexicon_ in &handler.lexicons {
-                let guard = lexicon_.lock().unwrap();
-                if &guard.name == lexicon_name {
-                    lexicon = Some(guard);
-                    break;
-                }
-            }
-            let Some(lexicon) = lexicon else {
+            let slot = find(&lexicons, guild_id, lexicon_name).map(|index| &lexicons[index]);
+            let Some(slot) = slot else {
                 return Response::failure("Query entry error", "The lexicon could not be found.");
             };
+            if let Err(response) = slot.require(user, member, Access::Read) {
+                return response;
+            }
             let Some(Value::String(word)) = &options[1].value else {
                 return Response::invalid_command();
             };
-            let c = word.chars().next().unwrap().to_uppercase().next().unwrap();
-            let Some(word_set) = lexicon.words.get(&c) else {
-                return Response::failure("Query entry error", "The word could not be found.");
+            let lexicon = match slot.try_lock() {
+                Ok(lexicon) => lexicon,
+                Err(response) => return response,
             };
-            let Some(entry) = word_set.get(word) else {
+            let word = lexicon.lookup(word);
+            let Some(entry) = lexicon.entry(&word) else {
                 return Response::failure("Query entry error", "The word could not be found.");
             };
-            Response::success(word, entry)
+            Response::success(&word, entry).footer(&lexicon.name)
         }
         "update" => {
             let options = &option.options;
@@ -142,24 +472,21 @@ pub fn run(handler: &Handler, user: &User, options: &[CommandDataOption]) -> Res
             let Some(Value::String(lexicon_name)) = &options[0].value else {
                 return Response::invalid_command();
             };
-            let mut index = None;
-            for (i, lexicon_) in handler.lexicons.iter().enumerate() {
-                let guard = lexicon_.lock().unwrap();
-                if &guard.name == lexicon_name {
-                    index = Some(i);
-                    break;
-                }
-            }
+            let index = find(&lexicons, guild_id, lexicon_name);
             let Some(index) = index else {
                 return Response::failure("Update entry error", "The lexicon could not be found.");
             };
+            let slot = &lexicons[index];
+            if let Err(response) = slot.require(user, member, Access::Suggest) {
+                return response;
+            }
             Response::modal(
                 |handler, response| {
                     response
                         .title("Update lexicon entry")
                         .set_components(handler.lexicon_update_modal.clone());
                 },
-                Modal::LexiconUpdate { index },
+                Modal::new("lexicon", "update", slot.name.clone()),
             )
         }
         "remove" => {
@@ -170,41 +497,39 @@ pub fn run(handler: &Handler, user: &User, options: &[CommandDataOption]) -> Res
             let Some(Value::String(lexicon_name)) = &options[0].value else {
                 return Response::invalid_command();
             };
-            let mut lexicon = None;
-            for lexicon_ in &handler.lexicons {
-                let guard = lexicon_.lock().unwrap();
-                if &guard.name == lexicon_name {
-                    lexicon = Some(guard);
-                    break;
-                }
-            }
-            let Some(mut lexicon) = lexicon else {
+            let slot = find(&lexicons, guild_id, lexicon_name).map(|index| &lexicons[index]);
+            let Some(slot) = slot else {
                 return Response::failure("Remove entry error", "The lexicon could not be found.");
             };
+            if let Err(response) = slot.require(user, member, Access::Edit) {
+                return response;
+            }
             let Some(Value::String(word)) = &options[1].value else {
                 return Response::invalid_command();
             };
-            let c = word.chars().next().unwrap().to_uppercase().next().unwrap();
+            // removing is deferred, so it may wait for another edit
+            let mut lexicon = slot.lock();
+            let word = lexicon.lookup(word);
+            let Some(c) = bucket(&word) else {
+                return Response::failure("Remove entry error", "The word could not be found.");
+            };
             let Some(word_set) = lexicon.words.get_mut(&c) else {
                 return Response::failure("Remove entry error", "The word could not be found.");
             };
-            if word_set.remove(word).is_none() {
+            let Some(description) = word_set.remove(&word) else {
                 return Response::failure("Remove entry error", "The word could not be found.");
-            }
+            };
             if word_set.is_empty() {
                 lexicon.words.remove(&c);
             }
-            if !update_lexicon(&handler.config, &lexicon) {
-                return Response::failure("Update error", "The lexicon could not be updated.");
-            }
-            if !update_lexicon_git(
-                &handler.config,
-                &lexicon,
-                &format!("[lexicon] Remove {word} - {}", user.name),
-            ) {
-                return Response::failure("Git error", "The lexicon could not be pushed to Git.");
-            }
-            Response::success("Success", "The word got removed.")
+            save(
+                handler,
+                &mut lexicon,
+                Change::new("Remove", &word, user),
+                Some(description),
+                Response::success("Success", "The word got removed."),
+            )
+            .unwrap_or_else(|response| response)
         }
         "list" => {
             let options = &option.options;
@@ -214,21 +539,21 @@ pub fn run(handler: &Handler, user: &User, options: &[CommandDataOption]) -> Res
             let Some(Value::String(lexicon_name)) = &options[0].value else {
                 return Response::invalid_command();
             };
-            let mut lexicon = None;
-            for lexicon_ in &handler.lexicons {
-                let guard = lexicon_.lock().unwrap();
-                if &guard.name == lexicon_name {
-                    lexicon = Some(guard);
-                    break;
-                }
-            }
-            let Some(lexicon) = lexicon else {
+            let slot = find(&lexicons, guild_id, lexicon_name).map(|index| &lexicons[index]);
+            let Some(slot) = slot else {
                 return Response::failure("List entry error", "The lexicon could not be found.");
             };
+            if let Err(response) = slot.require(user, member, Access::Read) {
+                return response;
+            }
+            let lexicon = match slot.try_lock() {
+                Ok(lexicon) => lexicon,
+                Err(response) => return response,
+            };
             let mut list = String::new();
             list.push_str("```");
-            for word_set in lexicon.words.values() {
-                for word in word_set.keys() {
+            for group in collation::group(&lexicon.collation, &lexicon.words) {
+                for (word, _) in group.entries {
                     list.push_str(word);
                     list.push('\n');
                 }
@@ -236,91 +561,625 @@ pub fn run(handler: &Handler, user: &User, options: &[CommandDataOption]) -> Res
             list.push_str("```");
             Response::success("List", list)
         }
+        "random" => {
+            let options = &option.options;
+            if option.options.len() != 1 {
+                return Response::invalid_command();
+            }
+            let Some(Value::String(lexicon_name)) = &options[0].value else {
+                return Response::invalid_command();
+            };
+            let slot = find(&lexicons, guild_id, lexicon_name).map(|index| &lexicons[index]);
+            let Some(slot) = slot else {
+                return Response::failure("Random entry error", "The lexicon could not be found.");
+            };
+            if let Err(response) = slot.require(user, member, Access::Read) {
+                return response;
+            }
+            let lexicon = match slot.try_lock() {
+                Ok(lexicon) => lexicon,
+                Err(response) => return response,
+            };
+            let entry = lexicon
+                .words
+                .values()
+                .flatten()
+                .choose(&mut rand::thread_rng());
+            let Some((word, description)) = entry else {
+                return Response::failure("Random entry error", "The lexicon is empty.");
+            };
+            Response::success(word, description).footer(&lexicon.name)
+        }
+        "quiz" => {
+            let options = &option.options;
+            if option.options.len() != 1 {
+                return Response::invalid_command();
+            }
+            let Some(Value::String(lexicon_name)) = &options[0].value else {
+                return Response::invalid_command();
+            };
+            let slot = find(&lexicons, guild_id, lexicon_name).map(|index| &lexicons[index]);
+            let Some(slot) = slot else {
+                return Response::failure("Quiz error", "The lexicon could not be found.");
+            };
+            if let Err(response) = slot.require(user, member, Access::Read) {
+                return response;
+            }
+            let lexicon = match slot.try_lock() {
+                Ok(lexicon) => lexicon,
+                Err(response) => return response,
+            };
+            quiz::start(handler, &lexicon, user)
+        }
+        "stats" => {
+            let options = &option.options;
+            if option.options.len() != 1 {
+                return Response::invalid_command();
+            }
+            let Some(Value::String(lexicon_name)) = &options[0].value else {
+                return Response::invalid_command();
+            };
+            let slot = find(&lexicons, guild_id, lexicon_name).map(|index| &lexicons[index]);
+            let Some(slot) = slot else {
+                return Response::failure("Statistics error", "The lexicon could not be found.");
+            };
+            if let Err(response) = slot.require(user, member, Access::Read) {
+                return response;
+            }
+            let lexicon = match slot.try_lock() {
+                Ok(lexicon) => lexicon,
+                Err(response) => return response,
+            };
+            stats::stats(&lexicon, &handler.changes.lock().unwrap())
+        }
+        "review" => {
+            let options = &option.options;
+            if option.options.len() != 1 {
+                return Response::invalid_command();
+            }
+            let Some(Value::String(lexicon_name)) = &options[0].value else {
+                return Response::invalid_command();
+            };
+            let slot = find(&lexicons, guild_id, lexicon_name).map(|index| &lexicons[index]);
+            let Some(slot) = slot else {
+                return Response::failure("Review error", "The lexicon could not be found.");
+            };
+            if let Err(response) = slot.require(user, member, Access::Edit) {
+                return response;
+            }
+            let lexicon = match slot.try_lock() {
+                Ok(lexicon) => lexicon,
+                Err(response) => return response,
+            };
+            let suggestions = handler.suggestions.lock().unwrap();
+            let Some(suggestion) = suggestions.pending(&lexicon.name).next() else {
+                return Response::success("Review", "There are no pending suggestions.");
+            };
+            review_prompt(&lexicon, &suggestions, suggestion)
+        }
+        "export" => {
+            let options = &option.options;
+            if option.options.len() != 1 {
+                return Response::invalid_command();
+            }
+            let Some(Value::String(lexicon_name)) = &options[0].value else {
+                return Response::invalid_command();
+            };
+            let slot = find(&lexicons, guild_id, lexicon_name).map(|index| &lexicons[index]);
+            let Some(slot) = slot else {
+                return Response::failure("Export error", "The lexicon could not be found.");
+            };
+            if let Err(response) = slot.require(user, member, Access::Read) {
+                return response;
+            }
+            let lexicon = match slot.try_lock() {
+                Ok(lexicon) => lexicon,
+                Err(response) => return response,
+            };
+            let Ok(rendered) = lexicon.rendered() else {
+                return Response::failure("Export error", "The lexicon could not be rendered.");
+            };
+            let name = lexicon
+                .target_file
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| format!("{}.md", lexicon.name));
+            Response::success("Export", format!("The lexicon {} as a file.", lexicon.name))
+                .attachment(name, rendered)
+        }
         _ => Response::unimplemented(),
     }
 }
 
-pub async fn handle_add(
+pub fn handle_add(
     handler: &Handler,
-    index: usize,
+    lexicon_name: &str,
     submission: &mut ModalSubmitInteraction,
 ) -> Response {
-    let mut rows = submission.data.components.drain(..);
-    let mut first = rows.next().unwrap().components.into_iter();
-    let mut second = rows.next().unwrap().components.into_iter();
-    let word = first.next().unwrap();
-    let ActionRowComponent::InputText(word) = word else {unreachable!()};
-    let word = word.value;
-    let description = second.next().unwrap();
-    let ActionRowComponent::InputText(description) = description else {unreachable!()};
-    let description = description.value;
-    let c = word.chars().next().unwrap().to_uppercase().next().unwrap();
-    let mut lexicon = handler.lexicons[index].lock().unwrap();
-    let word_set = lexicon.words.entry(c).or_insert_with(BTreeMap::new);
-    if word_set.contains_key(&word) {
-        return Response::failure("Add entry error", "The word already exists in the lexicon.");
-    }
-    word_set.insert(word.clone(), description);
-    if !update_lexicon(&handler.config, &lexicon) {
-        return Response::failure("Update error", "The lexicon could not be updated.");
-    }
-    if !update_lexicon_git(
-        &handler.config,
-        &lexicon,
-        &format!("[lexicon] Add {word} - {}", submission.user.name),
-    ) {
-        return Response::failure("Git error", "The lexicon could not be pushed to Git.");
+    let Ok([word, description]) = <[String; 2]>::try_from(text_inputs(submission)) else {
+        return Response::invalid_command();
+    };
+    let lexicons = handler.lexicons.read().unwrap();
+    let Some(index) = find(&lexicons, submission.guild_id, lexicon_name) else {
+        return Response::failure("Lexicon error", "The lexicon could not be found.");
+    };
+    let slot = &lexicons[index];
+    let member = submission.member.as_ref();
+    if let Err(response) = slot.require(&submission.user, member, Access::Suggest) {
+        return response;
     }
-    Response::success(
-        "Success",
-        format!("Successfully updated lexicon entry for word '{word}'."),
+    let mut lexicon = slot.lock();
+    let word = match validation::validate(&lexicon.validation, &word) {
+        Ok(word) => word,
+        Err(err) => return Response::failure("Invalid word", err),
+    };
+    if slot.access(&submission.user, member) < Access::Edit {
+        if lexicon.entry(&word).is_some() {
+            return Response::failure("Add entry error", "The word already exists in the lexicon.");
+        }
+        return suggest(
+            handler,
+            &lexicon,
+            SuggestionKind::Add,
+            word,
+            description,
+            &submission.user,
+        );
+    }
+    if let Err(response) = add_entry(&mut lexicon, &word, description) {
+        return response;
+    }
+    save(
+        handler,
+        &mut lexicon,
+        Change::new("Add", &word, &submission.user),
+        None,
+        Response::success(
+            "Success",
+            format!("Successfully updated lexicon entry for word '{word}'."),
+        ),
     )
+    .unwrap_or_else(|response| response)
 }
 
-pub async fn handle_update(
+pub fn handle_update(
     handler: &Handler,
-    index: usize,
+    lexicon_name: &str,
     submission: &mut ModalSubmitInteraction,
 ) -> Response {
-    let mut rows = submission.data.components.drain(..);
-    let mut first = rows.next().unwrap().components.into_iter();
-    let mut second = rows.next().unwrap().components.into_iter();
-    let word = first.next().unwrap();
-    let ActionRowComponent::InputText(word) = word else {unreachable!()};
-    let word = word.value;
-    let description = second.next().unwrap();
-    let ActionRowComponent::InputText(description) = description else {unreachable!()};
-    let description = description.value;
-    let c = word.chars().next().unwrap().to_uppercase().next().unwrap();
-    let mut lexicon = handler.lexicons[index].lock().unwrap();
-    let Some(word_set) = lexicon.words.get_mut(&c) else {
-        return Response::failure("Update entry error", "The word could not be found.");
-    };
-    let Some(entry) = word_set.get_mut(&word) else {
-        return Response::failure("Update entry error", "The word could not be found.");
+    let Ok([word, description]) = <[String; 2]>::try_from(text_inputs(submission)) else {
+        return Response::invalid_command();
     };
-    if entry == &description {
-        return Response::success("Success", "Nothing changed.");
-    }
-    *entry = description;
-    if !update_lexicon(&handler.config, &lexicon) {
-        return Response::failure("Update error", "The lexicon could not be updated.");
+    let lexicons = handler.lexicons.read().unwrap();
+    let Some(index) = find(&lexicons, submission.guild_id, lexicon_name) else {
+        return Response::failure("Lexicon error", "The lexicon could not be found.");
+    };
+    let slot = &lexicons[index];
+    let member = submission.member.as_ref();
+    if let Err(response) = slot.require(&submission.user, member, Access::Suggest) {
+        return response;
     }
-    if !update_lexicon_git(
-        &handler.config,
-        &lexicon,
-        &format!("[lexicon] Update {word} - {}", submission.user.name),
-    ) {
-        return Response::failure("Git error", "The lexicon could not be pushed to Git.");
+    let mut lexicon = slot.lock();
+    let word = lexicon.lookup(&word);
+    if slot.access(&submission.user, member) < Access::Edit {
+        match lexicon.entry(&word) {
+            None => return Response::failure("Update entry error", "The word could not be found."),
+            Some(entry) if entry == &description => {
+                return Response::success("Success", "Nothing changed.")
+            }
+            Some(_) => {}
+        }
+        return suggest(
+            handler,
+            &lexicon,
+            SuggestionKind::Update,
+            word,
+            description,
+            &submission.user,
+        );
     }
-    Response::success(
-        "Success",
-        format!("Successfully updated lexicon entry for word '{word}'."),
+    let previous = match update_entry(&mut lexicon, &word, description) {
+        Err(response) => return response,
+        Ok(None) => return Response::success("Success", "Nothing changed."),
+        Ok(Some(previous)) => previous,
+    };
+    save(
+        handler,
+        &mut lexicon,
+        Change::new("Update", &word, &submission.user),
+        Some(previous),
+        Response::success(
+            "Success",
+            format!("Successfully updated lexicon entry for word '{word}'."),
+        ),
     )
+    .unwrap_or_else(|response| response)
+}
+
+/// Shows a suggestion with buttons to approve or reject it and a menu to pick another one.
+fn review_prompt(
+    lexicon: &Lexicon,
+    suggestions: &Suggestions,
+    suggestion: &Suggestion,
+) -> Response {
+    let pending = suggestions.pending(&lexicon.name).collect::<Vec<_>>();
+    let text = format!(
+        "Suggested by {} ({} pending)\n\n**New description**\n{}",
+        suggestion.user_name,
+        pending.len(),
+        suggestion.description
+    );
+    let action = |kind: SuggestionKind| match kind {
+        SuggestionKind::Add => "Add",
+        SuggestionKind::Update => "Update",
+    };
+    let mut response = Response::prompt(
+        format!("{} '{}'", action(suggestion.kind), suggestion.word),
+        text,
+        vec![
+            Button::new(
+                format!("lexicon:review:approve:{}", suggestion.id),
+                "Approve",
+                ButtonStyle::Success,
+            ),
+            Button::new(
+                format!("lexicon:review:reject:{}", suggestion.id),
+                "Reject",
+                ButtonStyle::Danger,
+            ),
+        ],
+    );
+    if let SuggestionKind::Update = suggestion.kind {
+        if let Some(entry) = lexicon.entry(&suggestion.word) {
+            response = response.embed("Current description", entry);
+        }
+    }
+    if pending.len() > 1 {
+        // select menus hold at most 25 options with labels of at most 100 characters
+        let options = pending
+            .iter()
+            .take(25)
+            .map(|pending| {
+                let label = format!("{} '{}'", action(pending.kind), pending.word);
+                (label.chars().take(100).collect(), pending.id.to_string())
+            })
+            .collect();
+        response = response.select(Select::new(
+            "lexicon:review:show",
+            "Review another suggestion",
+            options,
+        ));
+    }
+    response
 }
 
+/// Shows the suggestion a reviewer picked from the review menu.
+fn handle_review_select(handler: &Handler, interaction: &MessageComponentInteraction) -> Response {
+    let Some(Ok(id)) = interaction.data.values.first().map(|id| id.parse::<u64>()) else {
+        return Response::invalid_command();
+    };
+    let lexicon_name = handler
+        .suggestions
+        .lock()
+        .unwrap()
+        .get(id)
+        .map(|suggestion| suggestion.lexicon.clone());
+    let Some(lexicon_name) = lexicon_name else {
+        return Response::failure("Review error", "The suggestion was already reviewed.");
+    };
+    let lexicons = handler.lexicons.read().unwrap();
+    let Some(index) = find(&lexicons, interaction.guild_id, &lexicon_name) else {
+        return Response::failure("Review error", "The lexicon could not be found.");
+    };
+    let slot = &lexicons[index];
+    if let Err(response) =
+        slot.require(&interaction.user, interaction.member.as_ref(), Access::Edit)
+    {
+        return response;
+    }
+    let lexicon = match slot.try_lock() {
+        Ok(lexicon) => lexicon,
+        Err(response) => return response,
+    };
+    let suggestions = handler.suggestions.lock().unwrap();
+    let Some(suggestion) = suggestions.get(id) else {
+        return Response::failure("Review error", "The suggestion was already reviewed.");
+    };
+    review_prompt(&lexicon, &suggestions, suggestion)
+}
+
+/// Applies or discards a suggestion when a reviewer presses one of the review buttons.
+pub fn handle_review(handler: &Handler, interaction: &MessageComponentInteraction) -> Response {
+    let mut parts = interaction.data.custom_id.split(':').skip(2);
+    let (Some(decision), Some(Ok(id))) = (parts.next(), parts.next().map(str::parse::<u64>)) else {
+        return Response::invalid_command();
+    };
+    let suggestion = handler.suggestions.lock().unwrap().get(id).cloned();
+    let Some(suggestion) = suggestion else {
+        return Response::failure("Review error", "The suggestion was already reviewed.");
+    };
+    let lexicons = handler.lexicons.read().unwrap();
+    let index = find(&lexicons, interaction.guild_id, &suggestion.lexicon);
+    let Some(index) = index else {
+        return Response::failure("Review error", "The lexicon could not be found.");
+    };
+    let slot = &lexicons[index];
+    if let Err(response) =
+        slot.require(&interaction.user, interaction.member.as_ref(), Access::Edit)
+    {
+        return response;
+    }
+    let mut lexicon = slot.lock();
+    let word = suggestion.word;
+    let response = match decision {
+        "approve" => {
+            let (action, applied) = match suggestion.kind {
+                SuggestionKind::Add => (
+                    "Add",
+                    add_entry(&mut lexicon, &word, suggestion.description).map(|()| Some(None)),
+                ),
+                SuggestionKind::Update => (
+                    "Update",
+                    update_entry(&mut lexicon, &word, suggestion.description)
+                        .map(|previous| previous.map(Some)),
+                ),
+            };
+            match applied {
+                // the suggestion no longer fits the lexicon, e.g. the word was added meanwhile,
+                // so it would fail again and is dropped like an applied one
+                Err(response) => Ok(response),
+                Ok(None) => Ok(Response::success("Approved", "Nothing changed.")),
+                Ok(Some(previous)) => save(
+                    handler,
+                    &mut lexicon,
+                    Change {
+                        action,
+                        word: &word,
+                        user_name: &suggestion.user_name,
+                        user_id: suggestion.user_id,
+                        approver: Some(&interaction.user),
+                    },
+                    previous,
+                    Response::success(
+                        "Approved",
+                        format!("The suggestion for '{word}' was applied."),
+                    ),
+                ),
+            }
+        }
+        "reject" => Ok(Response::success(
+            "Rejected",
+            format!("The suggestion for '{word}' was rejected."),
+        )),
+        _ => return Response::invalid_command(),
+    };
+    drop(lexicon);
+    drop(lexicons);
+    // a suggestion that could not be saved stays queued so it can be approved again
+    let Ok(response) = response else {
+        return response.unwrap_or_else(|response| response);
+    };
+    if !handler.suggestions.lock().unwrap().remove(id) {
+        error!("Could not save suggestions");
+    }
+    response
+}
+
+/// Suggests the words of the chosen lexicon that start with the typed text.
+fn complete_word(handler: &Handler, autocomplete: &AutocompleteInteraction) -> Vec<String> {
+    let Some(option) = autocomplete.data.options.first() else {
+        return Vec::new();
+    };
+    let string = |option: &CommandDataOption| match &option.value {
+        Some(Value::String(value)) => Some(value.clone()),
+        _ => None,
+    };
+    let lexicon_name = option
+        .options
+        .iter()
+        .find(|option| option.name == "lexicon")
+        .and_then(string);
+    let typed = option
+        .options
+        .iter()
+        .find(|option| option.focused)
+        .and_then(string);
+    let (Some(lexicon_name), Some(typed)) = (lexicon_name, typed) else {
+        return Vec::new();
+    };
+    let lexicons = handler.lexicons.read().unwrap();
+    let Some(index) = find(&lexicons, autocomplete.guild_id, &lexicon_name) else {
+        return Vec::new();
+    };
+    let slot = &lexicons[index];
+    if slot.access(&autocomplete.user, autocomplete.member.as_ref()) < Access::Read {
+        return Vec::new();
+    }
+    // suggestions are not worth waiting for an edit
+    let Ok(lexicon) = slot.try_lock() else {
+        return Vec::new();
+    };
+    let typed = validation::normalize(&lexicon.validation, &typed).to_lowercase();
+    lexicon
+        .words
+        .values()
+        .flat_map(|word_set| word_set.keys())
+        .filter(|word| word.to_lowercase().starts_with(&typed))
+        // Discord shows at most 25 choices
+        .take(25)
+        .cloned()
+        .collect()
+}
+
+pub struct LexiconCommand;
+
+#[async_trait]
+impl SlashCommand for LexiconCommand {
+    fn name(&self) -> &'static str {
+        "lexicon"
+    }
+
+    fn register<'a>(
+        &self,
+        handler: &Handler,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(handler, None, command)
+    }
+
+    async fn register_global(&self, handler: &Handler, http: &Http) -> serenity::Result<()> {
+        register_commands(handler, http).await
+    }
+
+    async fn register_guild(
+        &self,
+        handler: &Handler,
+        http: &Http,
+        guild_id: GuildId,
+    ) -> serenity::Result<()> {
+        if guild_scoped(handler) {
+            guild_id
+                .create_application_command(http, |command| {
+                    register(handler, Some(guild_id), command)
+                })
+                .await?;
+        } else {
+            // guilds only become known after `ready`, so a registration left over from when the
+            // lexicons were restricted is removed here
+            for command in guild_id.get_application_commands(http).await? {
+                if command.name == "lexicon" {
+                    guild_id
+                        .delete_application_command(http, command.id)
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        _ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Response {
+        // removing an entry pushes to git, which may take longer than Discord waits for a response
+        let removes = command
+            .data
+            .options
+            .first()
+            .map(|option| option.name == "remove")
+            .unwrap_or(false);
+        if removes {
+            let command = command.clone();
+            return Response::deferred(move |handler| run(handler, &command));
+        }
+        run(handler, command)
+    }
+
+    async fn modal(
+        &self,
+        _handler: &Handler,
+        modal: Modal,
+        submission: &mut ModalSubmitInteraction,
+    ) -> Response {
+        let mut submission = submission.clone();
+        match modal.kind.as_str() {
+            "add" => Response::deferred(move |handler| {
+                handle_add(handler, &modal.context, &mut submission)
+            }),
+            "update" => Response::deferred(move |handler| {
+                handle_update(handler, &modal.context, &mut submission)
+            }),
+            _ => Response::unimplemented(),
+        }
+    }
+
+    async fn component(
+        &self,
+        handler: &Handler,
+        component: &MessageComponentInteraction,
+    ) -> Response {
+        match component.data.custom_id.split(':').nth(1) {
+            Some("review") if component.data.custom_id == "lexicon:review:show" => {
+                handle_review_select(handler, component)
+            }
+            Some("review") => {
+                let component = component.clone();
+                Response::deferred(move |handler| handle_review(handler, &component))
+            }
+            Some("quiz") => quiz::handle_answer(handler, component).await,
+            _ => Response::invalid_command(),
+        }
+    }
+
+    async fn autocomplete(
+        &self,
+        handler: &Handler,
+        autocomplete: &AutocompleteInteraction,
+    ) -> Vec<String> {
+        complete_word(handler, autocomplete)
+    }
+}
+
+/// Whether any lexicon is restricted to specific guilds.
+///
+/// The lexicon command is then registered per guild instead of globally.
+pub fn guild_scoped(handler: &Handler) -> bool {
+    handler
+        .lexicons
+        .read()
+        .unwrap()
+        .iter()
+        .any(|slot| !slot.guilds.is_empty())
+}
+
+/// Registers the lexicon command with the current lexicons in every guild or globally.
+///
+/// Registrations of the other kind are removed so no stale lexicon choices remain.
+pub async fn register_commands(handler: &Handler, http: &Http) -> serenity::Result<()> {
+    let guilds = handler
+        .guilds
+        .lock()
+        .unwrap()
+        .iter()
+        .copied()
+        .collect::<Vec<_>>();
+    if guild_scoped(handler) {
+        for command in Command::get_global_application_commands(http).await? {
+            if command.name == "lexicon" {
+                Command::delete_global_application_command(http, command.id).await?;
+            }
+        }
+        for guild_id in guilds {
+            guild_id
+                .create_application_command(http, |command| {
+                    register(handler, Some(guild_id), command)
+                })
+                .await?;
+        }
+    } else {
+        Command::create_global_application_command(http, |command| {
+            register(handler, None, command)
+        })
+        .await?;
+        for guild_id in guilds {
+            for command in guild_id.get_application_commands(http).await? {
+                if command.name == "lexicon" {
+                    guild_id
+                        .delete_application_command(http, command.id)
+                        .await?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Registers the lexicon command globally or, with a guild, for that guild only.
 pub fn register<'a>(
     handler: &Handler,
+    guild_id: Option<GuildId>,
     command: &'a mut CreateApplicationCommand,
 ) -> &'a mut CreateApplicationCommand {
     command
@@ -339,11 +1198,7 @@ pub fn register<'a>(
                         .required(true)
                         .min_length(1)
                         .max_length(50);
-                    for lexicon in &handler.lexicons {
-                        let lexicon = lexicon.lock().unwrap();
-                        option.add_string_choice(&lexicon.name, &lexicon.name);
-                    }
-                    option
+                    add_lexicon_choices(handler, guild_id, option)
                 })
         })
         .create_option(|option| {
@@ -359,11 +1214,7 @@ pub fn register<'a>(
                         .required(true)
                         .min_length(1)
                         .max_length(50);
-                    for lexicon in &handler.lexicons {
-                        let lexicon = lexicon.lock().unwrap();
-                        option.add_string_choice(&lexicon.name, &lexicon.name);
-                    }
-                    option
+                    add_lexicon_choices(handler, guild_id, option)
                 })
                 .create_sub_option(|option| {
                     option
@@ -371,8 +1222,9 @@ pub fn register<'a>(
                         .description("The word")
                         .kind(CommandOptionType::String)
                         .required(true)
-                        .min_length(2)
-                        .max_length(50)
+                        .set_autocomplete(true)
+                        .min_length(1)
+                        .max_length(100)
                 })
         })
         .create_option(|option| {
@@ -388,11 +1240,7 @@ pub fn register<'a>(
                         .required(true)
                         .min_length(1)
                         .max_length(50);
-                    for lexicon in &handler.lexicons {
-                        let lexicon = lexicon.lock().unwrap();
-                        option.add_string_choice(&lexicon.name, &lexicon.name);
-                    }
-                    option
+                    add_lexicon_choices(handler, guild_id, option)
                 })
         })
         .create_option(|option| {
@@ -408,11 +1256,7 @@ pub fn register<'a>(
                         .required(true)
                         .min_length(1)
                         .max_length(50);
-                    for lexicon in &handler.lexicons {
-                        let lexicon = lexicon.lock().unwrap();
-                        option.add_string_choice(&lexicon.name, &lexicon.name);
-                    }
-                    option
+                    add_lexicon_choices(handler, guild_id, option)
                 })
                 .create_sub_option(|option| {
                     option
@@ -420,8 +1264,9 @@ pub fn register<'a>(
                         .description("The word")
                         .kind(CommandOptionType::String)
                         .required(true)
-                        .min_length(2)
-                        .max_length(50)
+                        .set_autocomplete(true)
+                        .min_length(1)
+                        .max_length(100)
                 })
         })
         .create_option(|option| {
@@ -437,74 +1282,411 @@ pub fn register<'a>(
                         .required(true)
                         .min_length(1)
                         .max_length(50);
-                    for lexicon in &handler.lexicons {
-                        let lexicon = lexicon.lock().unwrap();
-                        option.add_string_choice(&lexicon.name, &lexicon.name);
-                    }
+                    add_lexicon_choices(handler, guild_id, option)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("random")
+                .description("Show a random lexicon entry")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
                     option
+                        .name("lexicon")
+                        .description("The lexicon")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                        .min_length(1)
+                        .max_length(50);
+                    add_lexicon_choices(handler, guild_id, option)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("quiz")
+                .description("Guess the word to a lexicon entry")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("lexicon")
+                        .description("The lexicon")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                        .min_length(1)
+                        .max_length(50);
+                    add_lexicon_choices(handler, guild_id, option)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("stats")
+                .description("Show statistics of a lexicon")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("lexicon")
+                        .description("The lexicon")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                        .min_length(1)
+                        .max_length(50);
+                    add_lexicon_choices(handler, guild_id, option)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("export")
+                .description("Export a lexicon as a file")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("lexicon")
+                        .description("The lexicon")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                        .min_length(1)
+                        .max_length(50);
+                    add_lexicon_choices(handler, guild_id, option)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("review")
+                .description("Review suggested lexicon entries")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("lexicon")
+                        .description("The lexicon")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                        .min_length(1)
+                        .max_length(50);
+                    add_lexicon_choices(handler, guild_id, option)
                 })
         })
 }
 
-fn update_lexicon(config: &Config, lexicon: &Lexicon) -> bool {
-    if !git::pull(&config.git) {
-        return false;
+fn add_lexicon_choices<'a>(
+    handler: &Handler,
+    guild_id: Option<GuildId>,
+    option: &'a mut CreateApplicationCommandOption,
+) -> &'a mut CreateApplicationCommandOption {
+    for slot in handler.lexicons.read().unwrap().iter() {
+        if slot.available_in(guild_id) {
+            option.add_string_choice(&slot.name, &slot.name);
+        }
     }
-    let mut path = config.git.path.clone();
-    path.push(&lexicon.file);
-    let Ok(ser) = ron::to_string(&lexicon.words) else {
-        return false;
+    option
+}
+
+/// Finds a lexicon by name among the ones available in a guild.
+fn find(lexicons: &[Slot], guild_id: Option<GuildId>, name: &str) -> Option<usize> {
+    lexicons
+        .iter()
+        .position(|slot| slot.name == name && slot.available_in(guild_id))
+}
+
+enum Published {
+    Pushed,
+    /// Committed locally, the push is retried by [`sync`].
+    Pending,
+    TimedOut,
+    Failed,
+}
+
+/// A change to a lexicon and the people to credit for it.
+struct Change<'a> {
+    action: &'a str,
+    word: &'a str,
+    user_name: &'a str,
+    user_id: UserId,
+    /// The reviewer who approved a suggested change.
+    approver: Option<&'a User>,
+}
+
+impl<'a> Change<'a> {
+    fn new(action: &'a str, word: &'a str, user: &'a User) -> Self {
+        Self {
+            action,
+            word,
+            user_name: &user.name,
+            user_id: user.id,
+            approver: None,
+        }
+    }
+}
+
+/// The key a word is stored under in the lexicon file, `None` for an empty word.
+fn bucket(word: &str) -> Option<char> {
+    word.chars().next()?.to_uppercase().next()
+}
+
+fn add_entry(lexicon: &mut Lexicon, word: &str, description: String) -> Result<(), Response> {
+    let Some(c) = bucket(word) else {
+        return Err(Response::failure("Add entry error", "The word is empty."));
     };
-    if write(path, ser).is_err() {
-        return false;
+    let word_set = lexicon.words.entry(c).or_default();
+    if word_set.contains_key(word) {
+        return Err(Response::failure(
+            "Add entry error",
+            "The word already exists in the lexicon.",
+        ));
     }
-    let mut path = config.git.path.clone();
-    path.push(&lexicon.target_file);
-    let Ok(mut file) = File::create(path) else {
-        return false;
+    word_set.insert(word.to_string(), description);
+    Ok(())
+}
+
+/// Replaces the description of an entry, returns the previous description if it changed.
+fn update_entry(
+    lexicon: &mut Lexicon,
+    word: &str,
+    description: String,
+) -> Result<Option<String>, Response> {
+    let Some(entry) = bucket(word)
+        .and_then(|c| lexicon.words.get_mut(&c))
+        .and_then(|word_set| word_set.get_mut(word))
+    else {
+        return Err(Response::failure(
+            "Update entry error",
+            "The word could not be found.",
+        ));
     };
-    if file
-        .write_all(b"<!--THIS FILE IS AUTOMATICALLY GENERATED - DO NOT EDIT-->\n")
-        .is_err()
-    {
-        return false;
+    if entry == &description {
+        return Ok(None);
     }
-    for (c, word_set) in &lexicon.words {
-        if file.write_all(format!("\n## {c}\n").as_bytes()).is_err() {
-            return false;
+    Ok(Some(std::mem::replace(entry, description)))
+}
+
+/// Puts back the description a word had before an edit, removing the word if it was new.
+fn restore_entry(lexicon: &mut Lexicon, word: &str, previous: Option<String>) {
+    let Some(c) = bucket(word) else {
+        return;
+    };
+    match previous {
+        Some(description) => {
+            lexicon
+                .words
+                .entry(c)
+                .or_default()
+                .insert(word.to_string(), description);
         }
-        for (word, desc) in word_set {
-            if file.write_all(b"\n### ").is_err() {
-                return false;
-            }
-            if file.write_all(word.as_bytes()).is_err() {
-                return false;
-            }
-            if file.write_all(b"\n\n").is_err() {
-                return false;
+        None => {
+            if let Some(word_set) = lexicon.words.get_mut(&c) {
+                word_set.remove(word);
+                if word_set.is_empty() {
+                    lexicon.words.remove(&c);
+                }
             }
-            if file.write_all(desc.as_bytes()).is_err() {
-                return false;
+        }
+    }
+}
+
+/// Queues a change for review by an editor.
+fn suggest(
+    handler: &Handler,
+    lexicon: &Lexicon,
+    kind: SuggestionKind,
+    word: String,
+    description: String,
+    user: &User,
+) -> Response {
+    let mut suggestions = handler.suggestions.lock().unwrap();
+    if !suggestions.submit(&lexicon.name, kind, word, description, user) {
+        return Response::failure("Suggestion error", "The suggestion could not be saved.");
+    }
+    Response::success(
+        "Suggestion submitted",
+        "Your suggestion was submitted for review.",
+    )
+}
+
+/// Writes, commits and pushes an edited lexicon, responding with `success` once it is pushed.
+///
+/// If the change cannot be published, the working copy and the entry are reset to `previous`,
+/// the description of the word before the edit, and the failure is returned as the error.
+fn save(
+    handler: &Handler,
+    lexicon: &mut Lexicon,
+    change: Change,
+    previous: Option<String>,
+    success: Response,
+) -> Result<Response, Response> {
+    let repository = lexicon.repository(&handler.repositories);
+    let _lock = repository.lock();
+    let updated = update_lexicon(repository, lexicon);
+    // the pull is done, so this is the commit to return to if the change is not published
+    let head = git::head(&repository.config);
+    let published = updated.map(|()| update_lexicon_git(repository, lexicon, &change));
+    let response = match published {
+        Ok(Published::Pushed) => Ok(success),
+        Ok(Published::Pending) => Ok(Response::success(
+            "Saved",
+            "The change was saved and is pending sync.",
+        )),
+        Ok(Published::TimedOut) => Err(Response::failure(
+            "Git timeout",
+            "Committing or pushing the lexicon timed out, nothing was changed.",
+        )),
+        Ok(Published::Failed) => Err(Response::failure(
+            "Git error",
+            "The lexicon could not be pushed to Git.",
+        )),
+        Err(response) => Err(response),
+    };
+    if response.is_err() {
+        if let Some(head) = head {
+            if !git::reset(&repository.config, &head) {
+                error!(repository = %repository.name, "Could not reset git repository");
             }
-            if file.write_all(b"\n").is_err() {
-                return false;
+        }
+        restore_entry(lexicon, change.word, previous);
+        lexicon.modified = modified(
+            &repository
+                .config
+                .path
+                .join(lexicon.relative_path(&lexicon.file)),
+        );
+    } else if !handler
+        .changes
+        .lock()
+        .unwrap()
+        .record(&lexicon.name, &change)
+    {
+        error!("Could not save change log");
+    }
+    response
+}
+
+fn update_lexicon(repository: &Repository, lexicon: &mut Lexicon) -> Result<(), Response> {
+    let failure = || Response::failure("Update error", "The lexicon could not be updated.");
+    let pulled = git::pull(&repository.config);
+    if pulled != git::Status::Success {
+        // only an unreachable remote allows editing offline, conflicts have to be resolved
+        let offline = repository.config.offline_tolerant
+            && (pulled == git::Status::TimedOut || !git::reachable(&repository.config));
+        if !offline {
+            if pulled == git::Status::TimedOut {
+                return Err(Response::failure(
+                    "Git timeout",
+                    "Pulling the repository timed out, nothing was changed.",
+                ));
             }
+            error!(repository = %repository.name, "Could not pull git repository");
+            return Err(Response::failure(
+                "Git error",
+                "The remote changes could not be pulled or merged, nothing was changed.",
+            ));
         }
+        warn!(
+            repository = %repository.name,
+            "Could not pull git repository, editing offline"
+        );
+    }
+    let path = repository
+        .config
+        .path
+        .join(lexicon.relative_path(&lexicon.file));
+    let Ok(ser) = ron::to_string(&lexicon.words) else {
+        return Err(failure());
+    };
+    if write(&path, ser).is_err() {
+        return Err(failure());
     }
-    true
+    lexicon.modified = modified(&path);
+    let outputs = std::iter::once((&lexicon.target_file, &lexicon.render)).chain(
+        lexicon
+            .outputs
+            .iter()
+            .map(|output| (&output.file, &output.render)),
+    );
+    let groups = collation::group(&lexicon.collation, &lexicon.words);
+    for (file, render_config) in outputs {
+        let rendered = render::render(&lexicon.name, &groups, render_config);
+        let Ok(rendered) = rendered else {
+            return Err(failure());
+        };
+        let path = repository.config.path.join(lexicon.relative_path(file));
+        if write(path, rendered).is_err() {
+            return Err(failure());
+        }
+    }
+    Ok(())
 }
 
-fn update_lexicon_git(config: &Config, lexicon: &Lexicon, message: &str) -> bool {
-    if !git::stage(&config.git, lexicon.file.to_string_lossy().as_ref()) {
-        return false;
+fn update_lexicon_git(repository: &Repository, lexicon: &Lexicon, change: &Change) -> Published {
+    let config = &repository.config;
+    let file = lexicon.relative_path(&lexicon.file);
+    if !git::stage(config, file.to_string_lossy().as_ref()) {
+        return Published::Failed;
+    }
+    let outputs = std::iter::once(&lexicon.target_file)
+        .chain(lexicon.outputs.iter().map(|output| &output.file));
+    for file in outputs {
+        let file = lexicon.relative_path(file);
+        if !git::stage(config, file.to_string_lossy().as_ref()) {
+            return Published::Failed;
+        }
+    }
+    let commit = &config.commit;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+        .to_string();
+    let identity = |name: &str, id: UserId| {
+        let id = id.to_string();
+        let values = [
+            ("action", change.action),
+            ("word", change.word),
+            ("lexicon", &lexicon.name),
+            ("user", name),
+            ("user-id", &id),
+            ("timestamp", &timestamp),
+        ];
+        let message = template::render(&commit.message, &values);
+        let identity = format!("{name} <{}>", template::render(&commit.email, &values));
+        (message, identity)
+    };
+    let (mut message, user_author) = identity(change.user_name, change.user_id);
+    if let Some(approver) = change.approver {
+        let (_, approver) = identity(&approver.name, approver.id);
+        message.push_str(&format!("\n\nApproved-by: {approver}"));
+    }
+    let author = match commit.author {
+        CommitAuthor::Bot => None,
+        CommitAuthor::User => Some(user_author.as_str()),
+        CommitAuthor::CoAuthor => {
+            let separator = if change.approver.is_some() {
+                "\n"
+            } else {
+                "\n\n"
+            };
+            message.push_str(&format!("{separator}Co-authored-by: {user_author}"));
+            None
+        }
+    };
+    match git::commit(config, &message, author) {
+        git::Status::Success => {}
+        git::Status::Failure => return Published::Failed,
+        git::Status::TimedOut => return Published::TimedOut,
+    }
+    let pushed = git::push(config);
+    repository.set_synced(pushed == git::Status::Success);
+    if pushed == git::Status::Success {
+        // the push includes every commit from the outbox
+        git::clear_outbox(config);
+        return Published::Pushed;
     }
-    if !git::stage(&config.git, lexicon.target_file.to_string_lossy().as_ref()) {
-        return false;
+    let summary = message.lines().next().unwrap_or_default();
+    // a push the reachable remote rejected would be rejected again, so it is not kept pending
+    if config.offline_tolerant && !git::reachable(config) && git::queue(config, summary) {
+        return Published::Pending;
     }
-    if !git::commit(&config.git, message) {
-        return false;
+    if pushed == git::Status::TimedOut {
+        return Published::TimedOut;
     }
-    git::push(&config.git)
+    Published::Failed
 }
 
 pub fn create_add_modal() -> CreateComponents {
@@ -517,8 +1699,8 @@ pub fn create_add_modal() -> CreateComponents {
                     .custom_id("modal:lexicon:add:0")
                     .style(InputTextStyle::Short)
                     .required(true)
-                    .min_length(2)
-                    .max_length(50)
+                    .min_length(1)
+                    .max_length(100)
             })
         })
         .create_action_row(|row| {
@@ -544,8 +1726,8 @@ pub fn create_update_modal() -> CreateComponents {
                     .custom_id("modal:lexicon:update:0")
                     .style(InputTextStyle::Short)
                     .required(true)
-                    .min_length(2)
-                    .max_length(50)
+                    .min_length(1)
+                    .max_length(100)
             })
         })
         .create_action_row(|row| {
@@ -0,0 +1,519 @@
+use std::path::{Component, Path, PathBuf};
+
+use serenity::{
+    async_trait,
+    builder::{CreateApplicationCommand, CreateApplicationCommandOption},
+    client::Context,
+    json::Value,
+    model::{
+        guild::Member,
+        prelude::{
+            command::CommandOptionType,
+            interaction::application_command::{
+                ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+            },
+        },
+        Permissions,
+    },
+};
+use tracing::error;
+
+use super::{find, load, register_commands, Access, Slot};
+use crate::{check, commands::SlashCommand, AccessRule, Config, Handler, LexiconConfig, Response};
+
+pub async fn run(
+    handler: &Handler,
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+) -> Response {
+    let options = &command.data.options;
+    if options.len() != 1 {
+        return Response::invalid_command();
+    }
+    let option = &options[0];
+    let result = match option.name.as_str() {
+        "create" => create(handler, command, &option.options),
+        "delete" => delete(handler, command, &option.options),
+        "rename" => rename(handler, command, &option.options),
+        "configure" => configure(handler, command, &option.options),
+        _ => return Response::unimplemented(),
+    };
+    let response = match result {
+        Ok(response) => response,
+        Err(response) => return response,
+    };
+    if !handler.save_config() {
+        return Response::failure(
+            "Config error",
+            "The change was applied but the configuration could not be saved.",
+        );
+    }
+    if let Err(err) = register_commands(handler, &ctx.http).await {
+        error!("Could not update lexicon command: {err}");
+        return Response::failure(
+            "Discord error",
+            "The change was applied but the lexicon command could not be updated.",
+        );
+    }
+    response
+}
+
+fn create(
+    handler: &Handler,
+    command: &ApplicationCommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<Response, Response> {
+    if !manages_guild(command.member.as_ref()) {
+        return Err(Response::failure(
+            "Permission denied",
+            "You need the Manage Server permission to create lexicons.",
+        ));
+    }
+    let (Some(name), Some(file), Some(target_file)) = (
+        string(options, "name"),
+        path(options, "file")?,
+        path(options, "target-file")?,
+    ) else {
+        return Err(Response::invalid_command());
+    };
+    let lexicon_config = LexiconConfig {
+        name: name.to_string(),
+        repository: string(options, "repository").map(str::to_string),
+        directory: path(options, "directory")?.unwrap_or_default(),
+        file,
+        target_file,
+        access: Default::default(),
+        // a new lexicon belongs to the server that created it until it is configured otherwise
+        guilds: command.guild_id.into_iter().collect(),
+        render: Default::default(),
+        outputs: Vec::new(),
+        collation: Default::default(),
+        validation: Default::default(),
+        word_of_the_day: None,
+    };
+    let mut config = handler.config.lock().unwrap();
+    let mut lexicons = handler.lexicons.write().unwrap();
+    if lexicons.len() >= 25 {
+        return Err(Response::failure(
+            "Create error",
+            "There can be at most 25 lexicons.",
+        ));
+    }
+    if lexicons.iter().any(|slot| slot.name == name) {
+        return Err(Response::failure(
+            "Create error",
+            "A lexicon with this name already exists.",
+        ));
+    }
+    validated(&mut config, |lexicon_configs| {
+        lexicon_configs.push(lexicon_config.clone())
+    })?;
+    let slot = load(&handler.repositories, lexicon_config).map_err(|err| {
+        config.lexicons.pop();
+        Response::failure(
+            "Create error",
+            format!("The lexicon could not be loaded: {err}"),
+        )
+    })?;
+    lexicons.push(slot);
+    Ok(Response::success(
+        "Success",
+        format!("Created lexicon '{name}'."),
+    ))
+}
+
+fn delete(
+    handler: &Handler,
+    command: &ApplicationCommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<Response, Response> {
+    let Some(name) = string(options, "lexicon") else {
+        return Err(Response::invalid_command());
+    };
+    let mut config = handler.config.lock().unwrap();
+    let mut lexicons = handler.lexicons.write().unwrap();
+    let index = authorize(&lexicons, command, name)?;
+    if lexicons.len() == 1 {
+        return Err(Response::failure(
+            "Delete error",
+            "The last lexicon cannot be deleted.",
+        ));
+    }
+    // the files stay in the repository
+    lexicons.remove(index);
+    config.lexicons.retain(|lexicon| lexicon.name != name);
+    handler.overrides.lock().unwrap().remove_lexicon(name);
+    if !handler.suggestions.lock().unwrap().discard(name) {
+        error!("Could not save suggestions");
+    }
+    Ok(Response::success(
+        "Success",
+        format!("Deleted lexicon '{name}'."),
+    ))
+}
+
+fn rename(
+    handler: &Handler,
+    command: &ApplicationCommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<Response, Response> {
+    let (Some(name), Some(new_name)) = (string(options, "lexicon"), string(options, "name")) else {
+        return Err(Response::invalid_command());
+    };
+    let mut config = handler.config.lock().unwrap();
+    let mut lexicons = handler.lexicons.write().unwrap();
+    let index = authorize(&lexicons, command, name)?;
+    if lexicons.iter().any(|slot| slot.name == new_name) {
+        return Err(Response::failure(
+            "Rename error",
+            "A lexicon with this name already exists.",
+        ));
+    }
+    validated(&mut config, |lexicon_configs| {
+        if let Some(lexicon) = lexicon_configs
+            .iter_mut()
+            .find(|lexicon| lexicon.name == name)
+        {
+            lexicon.name = new_name.to_string();
+        }
+    })?;
+    let slot = &mut lexicons[index];
+    slot.name = new_name.to_string();
+    slot.lock().name = new_name.to_string();
+    handler
+        .overrides
+        .lock()
+        .unwrap()
+        .rename_lexicon(name, new_name);
+    if !handler.suggestions.lock().unwrap().rename(name, new_name) {
+        error!("Could not save suggestions");
+    }
+    if !handler.changes.lock().unwrap().rename(name, new_name) {
+        error!("Could not save change log");
+    }
+    handler.quizzes.lock().unwrap().rename(name, new_name);
+    if !handler.quiz_scores.lock().unwrap().rename(name, new_name) {
+        error!("Could not save quiz scores");
+    }
+    if !handler
+        .word_of_the_day
+        .lock()
+        .unwrap()
+        .rename(name, new_name)
+    {
+        error!("Could not save word of the day history");
+    }
+    Ok(Response::success(
+        "Success",
+        format!("Renamed lexicon '{name}' to '{new_name}'."),
+    ))
+}
+
+fn configure(
+    handler: &Handler,
+    command: &ApplicationCommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<Response, Response> {
+    let Some(name) = string(options, "lexicon") else {
+        return Err(Response::invalid_command());
+    };
+    let target_file = path(options, "target-file")?;
+    let editor_role = options
+        .iter()
+        .find(|option| option.name == "editor-role")
+        .and_then(|option| match &option.resolved {
+            Some(CommandDataOptionValue::Role(role)) => Some(role.id),
+            _ => None,
+        });
+    let restrict = options
+        .iter()
+        .find(|option| option.name == "restrict-to-guild")
+        .and_then(|option| match option.value {
+            Some(Value::Bool(restrict)) => Some(restrict),
+            _ => None,
+        });
+    if target_file.is_none() && editor_role.is_none() && restrict.is_none() {
+        return Err(Response::failure(
+            "Configure error",
+            "Nothing to configure.",
+        ));
+    }
+    let mut config = handler.config.lock().unwrap();
+    let mut lexicons = handler.lexicons.write().unwrap();
+    let index = authorize(&lexicons, command, name)?;
+    if !config.lexicons.iter().any(|lexicon| lexicon.name == name) {
+        return Err(Response::failure(
+            "Configure error",
+            "The lexicon could not be found.",
+        ));
+    }
+    let rule = editor_role.map(|role| AccessRule {
+        roles: vec![role],
+        users: Vec::new(),
+    });
+    let guilds = restrict.map(|restrict| {
+        if restrict {
+            command.guild_id.into_iter().collect()
+        } else {
+            Vec::new()
+        }
+    });
+    validated(&mut config, |lexicon_configs| {
+        let Some(lexicon_config) = lexicon_configs
+            .iter_mut()
+            .find(|lexicon| lexicon.name == name)
+        else {
+            return;
+        };
+        if let Some(target_file) = &target_file {
+            lexicon_config.target_file = target_file.clone();
+        }
+        if let Some(rule) = &rule {
+            lexicon_config.access.edit = Some(rule.clone());
+        }
+        if let Some(guilds) = &guilds {
+            lexicon_config.guilds = guilds.clone();
+        }
+    })?;
+    let slot = &mut lexicons[index];
+    let mut lexicon = slot.lock();
+    let mut changes = Vec::new();
+    if let Some(target_file) = target_file {
+        changes.push(format!("Target file: {}", target_file.display()));
+        lexicon.target_file = target_file;
+    }
+    drop(lexicon);
+    if let (Some(role), Some(rule)) = (editor_role, rule) {
+        changes.push(format!("Editor role: <@&{role}>"));
+        slot.access.edit = Some(rule);
+    }
+    if let Some(guilds) = guilds {
+        if guilds.is_empty() {
+            changes.push("Available in every server".to_string());
+        } else {
+            changes.push("Restricted to this server".to_string());
+        }
+        slot.guilds = guilds;
+    }
+    Ok(Response::success(
+        format!("Configured '{name}'"),
+        changes.join("\n"),
+    ))
+}
+
+/// Changes the lexicon configs, keeping the change only if the configuration stays valid.
+fn validated(
+    config: &mut Config,
+    change: impl FnOnce(&mut Vec<LexiconConfig>),
+) -> Result<(), Response> {
+    let previous = config.lexicons.clone();
+    change(&mut config.lexicons);
+    let errors = check::validate(config);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    config.lexicons = previous;
+    let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+    Err(Response::failure(
+        "Config error",
+        format!(
+            "The change would make the configuration invalid:\n{}",
+            errors.join("\n")
+        ),
+    ))
+}
+
+/// Finds a lexicon the user may administrate.
+///
+/// The Manage Server permission only covers lexicons restricted to the server the command is
+/// used in, lexicons shared with other servers need the admin access of the lexicon.
+fn authorize(
+    lexicons: &[Slot],
+    command: &ApplicationCommandInteraction,
+    name: &str,
+) -> Result<usize, Response> {
+    let Some(index) = find(lexicons, command.guild_id, name) else {
+        return Err(Response::failure(
+            "Lexicon error",
+            "The lexicon could not be found.",
+        ));
+    };
+    let member = command.member.as_ref();
+    let slot = &lexicons[index];
+    let own = matches!(command.guild_id, Some(guild_id) if slot.guilds == [guild_id]);
+    if !own || !manages_guild(member) {
+        slot.require(&command.user, member, Access::Admin)?;
+    }
+    Ok(index)
+}
+
+fn manages_guild(member: Option<&Member>) -> bool {
+    matches!(
+        member.and_then(|member| member.permissions),
+        Some(permissions) if permissions.manage_guild()
+    )
+}
+
+fn string<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match &option.value {
+            Some(Value::String(value)) => Some(value.as_str()),
+            _ => None,
+        })
+}
+
+/// Reads a path option, rejecting paths that could leave the repository.
+fn path(options: &[CommandDataOption], name: &str) -> Result<Option<PathBuf>, Response> {
+    let Some(path) = string(options, name) else {
+        return Ok(None);
+    };
+    let path = Path::new(path);
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(Response::failure(
+            "Path error",
+            format!("The {name} must be a relative path inside the repository."),
+        ));
+    }
+    Ok(Some(path.to_path_buf()))
+}
+
+pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("lexicon-admin")
+        .description("Manages the lexicons")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)
+        .create_option(|option| {
+            option
+                .name("create")
+                .description("Create a lexicon")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|option| {
+                    option
+                        .name("name")
+                        .description("The name of the lexicon")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                        .min_length(1)
+                        .max_length(50)
+                })
+                .create_sub_option(|option| {
+                    path_option(option, "file", "The lexicon file").required(true)
+                })
+                .create_sub_option(|option| {
+                    path_option(option, "target-file", "The generated file").required(true)
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("repository")
+                        .description("The repository, the default repository if absent")
+                        .kind(CommandOptionType::String)
+                        .min_length(1)
+                        .max_length(100)
+                })
+                .create_sub_option(|option| {
+                    path_option(option, "directory", "The directory of the lexicon files")
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("delete")
+                .description("Delete a lexicon, its files stay in the repository")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(lexicon_option)
+        })
+        .create_option(|option| {
+            option
+                .name("rename")
+                .description("Rename a lexicon")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(lexicon_option)
+                .create_sub_option(|option| {
+                    option
+                        .name("name")
+                        .description("The new name of the lexicon")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                        .min_length(1)
+                        .max_length(50)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("configure")
+                .description("Configure a lexicon")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(lexicon_option)
+                .create_sub_option(|option| {
+                    path_option(option, "target-file", "The generated file")
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("editor-role")
+                        .description("The role allowed to edit the lexicon")
+                        .kind(CommandOptionType::Role)
+                })
+                .create_sub_option(|option| {
+                    option
+                        .name("restrict-to-guild")
+                        .description("Whether the lexicon is only available in this server")
+                        .kind(CommandOptionType::Boolean)
+                })
+        })
+}
+
+fn lexicon_option(
+    option: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+    option
+        .name("lexicon")
+        .description("The lexicon")
+        .kind(CommandOptionType::String)
+        .required(true)
+        .min_length(1)
+        .max_length(50)
+}
+
+fn path_option<'a>(
+    option: &'a mut CreateApplicationCommandOption,
+    name: &str,
+    description: &str,
+) -> &'a mut CreateApplicationCommandOption {
+    option
+        .name(name)
+        .description(description)
+        .kind(CommandOptionType::String)
+        .min_length(1)
+        .max_length(200)
+}
+
+pub struct LexiconAdminCommand;
+
+#[async_trait]
+impl SlashCommand for LexiconAdminCommand {
+    fn name(&self) -> &'static str {
+        "lexicon-admin"
+    }
+
+    fn register<'a>(
+        &self,
+        _handler: &Handler,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Response {
+        run(handler, ctx, command).await
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use serenity::model::{
+    id::UserId,
+    prelude::{
+        component::ButtonStyle, interaction::message_component::MessageComponentInteraction,
+    },
+    user::User,
+};
+use tracing::error;
+
+use super::{find, Access, Lexicon};
+use crate::{error::Error, persist, Button, Handler, Response};
+
+/// Number of words offered per question.
+const OPTIONS: usize = 4;
+
+/// How long a question can be answered.
+const TTL: Duration = Duration::from_secs(15 * 60);
+
+/// A question waiting for an answer.
+struct Quiz {
+    lexicon: String,
+    user_id: UserId,
+    options: Vec<String>,
+    answer: usize,
+    expires: Instant,
+}
+
+/// Questions that were asked but not answered yet.
+///
+/// Questions are keyed by a random nonce so buttons from before a restart never match a newer
+/// question.
+#[derive(Default)]
+pub struct Quizzes {
+    pending: HashMap<u64, Quiz>,
+}
+
+impl Quizzes {
+    fn evict(&mut self) {
+        let now = Instant::now();
+        self.pending.retain(|_, quiz| quiz.expires > now);
+    }
+
+    /// Remembers a question and returns its nonce.
+    fn insert(&mut self, quiz: Quiz) -> u64 {
+        self.evict();
+        let mut id = rand::random();
+        while self.pending.contains_key(&id) {
+            id = rand::random();
+        }
+        self.pending.insert(id, quiz);
+        id
+    }
+
+    /// The question for a nonce, `None` if it is unknown or expired.
+    fn get(&mut self, id: u64) -> Option<&Quiz> {
+        self.evict();
+        self.pending.get(&id)
+    }
+
+    /// Points the open questions of a renamed lexicon to its new name.
+    pub fn rename(&mut self, lexicon: &str, name: &str) {
+        for quiz in self.pending.values_mut() {
+            if quiz.lexicon == lexicon {
+                quiz.lexicon = name.to_string();
+            }
+        }
+    }
+}
+
+/// The persisted quiz results per user and lexicon.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Scores {
+    users: BTreeMap<UserId, BTreeMap<String, Score>>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+struct Score {
+    answered: u64,
+    correct: u64,
+    streak: u64,
+    best_streak: u64,
+}
+
+impl Scores {
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let mut scores = persist::load::<Self>(&path)?;
+        scores.path = path;
+        Ok(scores)
+    }
+
+    fn save(&self) -> bool {
+        persist::save(&self.path, self)
+    }
+
+    /// Moves the scores of a renamed lexicon to its new name.
+    pub fn rename(&mut self, lexicon: &str, name: &str) -> bool {
+        for scores in self.users.values_mut() {
+            if let Some(score) = scores.remove(lexicon) {
+                scores.insert(name.to_string(), score);
+            }
+        }
+        self.save()
+    }
+
+    /// Records an answer and returns the updated score.
+    fn record(&mut self, user_id: UserId, lexicon: &str, correct: bool) -> Score {
+        let score = self
+            .users
+            .entry(user_id)
+            .or_default()
+            .entry(lexicon.to_string())
+            .or_default();
+        score.answered += 1;
+        if correct {
+            score.correct += 1;
+            score.streak += 1;
+            score.best_streak = score.best_streak.max(score.streak);
+        } else {
+            score.streak = 0;
+        }
+        let score = *score;
+        if !self.save() {
+            error!("Could not save quiz scores");
+        }
+        score
+    }
+}
+
+/// Asks for the word to a random description of the lexicon.
+pub fn start(handler: &Handler, lexicon: &Lexicon, user: &User) -> Response {
+    let mut rng = rand::thread_rng();
+    let entries = lexicon.words.values().flatten().collect::<Vec<_>>();
+    if entries.len() < 2 {
+        return Response::failure(
+            "Quiz error",
+            "The lexicon needs at least two entries for a quiz.",
+        );
+    }
+    let mut options = entries
+        .choose_multiple(&mut rng, OPTIONS)
+        .copied()
+        .collect::<Vec<_>>();
+    options.shuffle(&mut rng);
+    let answer = rng.gen_range(0..options.len());
+    let (word, description) = options[answer];
+    // don't give the answer away in the question
+    let description = description.replace(word.as_str(), "\\_\\_\\_");
+    let quiz = Quiz {
+        lexicon: lexicon.name.clone(),
+        user_id: user.id,
+        options: options.iter().map(|(word, _)| word.to_string()).collect(),
+        answer,
+        expires: Instant::now() + TTL,
+    };
+    let labels = quiz
+        .options
+        .iter()
+        .map(|word| word.chars().take(80).collect::<String>())
+        .collect::<Vec<_>>();
+    let id = handler.quizzes.lock().unwrap().insert(quiz);
+    let buttons = labels
+        .into_iter()
+        .enumerate()
+        .map(|(index, label)| {
+            Button::new(
+                format!("lexicon:quiz:{id:016x}:{index}"),
+                label,
+                ButtonStyle::Primary,
+            )
+        })
+        .collect();
+    Response::prompt(
+        format!("Quiz: {}", lexicon.name),
+        format!("Which word is described?\n\n{description}"),
+        buttons,
+    )
+}
+
+/// Checks the answer when the user presses one of the quiz buttons.
+pub async fn handle_answer(
+    handler: &Handler,
+    interaction: &MessageComponentInteraction,
+) -> Response {
+    let mut parts = interaction.data.custom_id.split(':').skip(2);
+    let (Some(Ok(id)), Some(Ok(choice))) = (
+        parts.next().map(|id| u64::from_str_radix(id, 16)),
+        parts.next().map(str::parse::<usize>),
+    ) else {
+        return Response::invalid_command();
+    };
+    let mut quizzes = handler.quizzes.lock().unwrap();
+    let Some(quiz) = quizzes.get(id) else {
+        return Response::failure(
+            "Quiz error",
+            "The question was already answered or has expired.",
+        )
+        .ephemeral();
+    };
+    if quiz.user_id != interaction.user.id {
+        return Response::failure("Quiz error", "This question is for someone else.").ephemeral();
+    }
+    let quiz = quizzes.pending.remove(&id).unwrap();
+    drop(quizzes);
+    let lexicons = handler.lexicons.read().unwrap();
+    // access may have changed since the question was asked
+    if let Some(index) = find(&lexicons, interaction.guild_id, &quiz.lexicon) {
+        let slot = &lexicons[index];
+        if let Err(response) =
+            slot.require(&interaction.user, interaction.member.as_ref(), Access::Read)
+        {
+            return response;
+        }
+    }
+    drop(lexicons);
+    let correct = choice == quiz.answer;
+    let score = handler
+        .quiz_scores
+        .lock()
+        .unwrap()
+        .record(quiz.user_id, &quiz.lexicon, correct);
+    let summary = format!(
+        "Score: {}/{}, streak: {} (best: {})",
+        score.correct, score.answered, score.streak, score.best_streak
+    );
+    let word = &quiz.options[quiz.answer];
+    let response = if correct {
+        Response::success("Correct!", format!("The word is '{word}'."))
+    } else {
+        Response::failure("Wrong", format!("The word is '{word}'."))
+    };
+    response.footer(summary)
+}
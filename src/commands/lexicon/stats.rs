@@ -0,0 +1,146 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serenity::model::id::UserId;
+use tracing::error;
+
+use super::{Change, Lexicon};
+use crate::{collation, error::Error, persist, Response};
+
+/// Number of entries shown in the recent additions and the leaderboard.
+const TOP: usize = 5;
+
+/// The persisted record of every published change to a lexicon.
+#[derive(Default, Deserialize, Serialize)]
+pub struct ChangeLog {
+    changes: Vec<Record>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Record {
+    lexicon: String,
+    action: String,
+    word: String,
+    user_name: String,
+    user_id: UserId,
+    /// Seconds since the Unix epoch.
+    timestamp: u64,
+}
+
+impl ChangeLog {
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let mut changes = persist::load::<Self>(&path)?;
+        changes.path = path;
+        Ok(changes)
+    }
+
+    fn save(&self) -> bool {
+        persist::save(&self.path, self)
+    }
+
+    /// Records a published change and persists the log.
+    pub(super) fn record(&mut self, lexicon: &str, change: &Change) -> bool {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        self.changes.push(Record {
+            lexicon: lexicon.to_string(),
+            action: change.action.to_string(),
+            word: change.word.to_string(),
+            user_name: change.user_name.to_string(),
+            user_id: change.user_id,
+            timestamp,
+        });
+        self.save()
+    }
+
+    /// Moves the changes of a renamed lexicon and persists the log.
+    pub fn rename(&mut self, lexicon: &str, name: &str) -> bool {
+        for change in &mut self.changes {
+            if change.lexicon == lexicon {
+                change.lexicon = name.to_string();
+            }
+        }
+        self.save()
+    }
+}
+
+/// Summarizes the entries of a lexicon and who contributed to it.
+pub fn stats(lexicon: &Lexicon, changes: &ChangeLog) -> Response {
+    let groups = collation::group(&lexicon.collation, &lexicon.words);
+    let count = groups
+        .iter()
+        .map(|group| group.entries.len())
+        .sum::<usize>();
+    let mut response = Response::success(format!("Statistics: {}", lexicon.name), "").field(
+        "Entries",
+        count.to_string(),
+        true,
+    );
+    let length = groups
+        .iter()
+        .flat_map(|group| &group.entries)
+        .map(|(_, description)| description.chars().count())
+        .sum::<usize>();
+    if let Some(average) = length.checked_div(count) {
+        let letters = groups
+            .iter()
+            .map(|group| format!("{}: {}", group.letter, group.entries.len()))
+            .collect::<Vec<_>>();
+        response = response
+            .field(
+                "Average description length",
+                format!("{average} characters"),
+                true,
+            )
+            .field("Entries per letter", letters.join(", "), false);
+    }
+    let records = changes
+        .changes
+        .iter()
+        .filter(|record| record.lexicon == lexicon.name)
+        .collect::<Vec<_>>();
+    let recent = records
+        .iter()
+        .rev()
+        .filter(|record| record.action == "Add" && lexicon.entry(&record.word).is_some())
+        .take(TOP)
+        .map(|record| {
+            format!(
+                "{} by {} (<t:{}:R>)",
+                record.word, record.user_name, record.timestamp
+            )
+        })
+        .collect::<Vec<_>>();
+    if !recent.is_empty() {
+        response = response.field("Recent additions", recent.join("\n"), false);
+    }
+    let mut contributors = HashMap::<UserId, (&str, usize)>::new();
+    for record in &records {
+        let contributor = contributors
+            .entry(record.user_id)
+            .or_insert((&record.user_name, 0));
+        // the latest name of the user is shown
+        contributor.0 = &record.user_name;
+        contributor.1 += 1;
+    }
+    let mut contributors = contributors.into_values().collect::<Vec<_>>();
+    contributors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    if !contributors.is_empty() {
+        let leaderboard = contributors
+            .iter()
+            .take(TOP)
+            .enumerate()
+            .map(|(rank, (name, changes))| format!("{}. {name}: {changes} changes", rank + 1))
+            .collect::<Vec<_>>();
+        response = response.field("Top contributors", leaderboard.join("\n"), false);
+    }
+    response
+}
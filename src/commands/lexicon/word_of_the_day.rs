@@ -0,0 +1,167 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rand::seq::IteratorRandom;
+use serde::{Deserialize, Serialize};
+use serenity::{http::Http, model::id::ChannelId, utils::Color};
+use tracing::error;
+
+use super::Slot;
+use crate::{error::Error, persist};
+
+/// The entries already posted per lexicon, so no word repeats before all were posted.
+#[derive(Default, Deserialize, Serialize)]
+pub struct History {
+    lexicons: BTreeMap<String, LexiconHistory>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct LexiconHistory {
+    posted: BTreeSet<String>,
+    /// Days since the Unix epoch of the last post.
+    last_day: Option<u64>,
+}
+
+struct Post {
+    lexicon: String,
+    channel: ChannelId,
+    word: String,
+    description: String,
+}
+
+impl History {
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let mut history = persist::load::<Self>(&path)?;
+        history.path = path;
+        Ok(history)
+    }
+
+    fn save(&self) -> bool {
+        persist::save(&self.path, self)
+    }
+
+    /// Moves the history of a renamed lexicon to its new name.
+    pub fn rename(&mut self, lexicon: &str, name: &str) -> bool {
+        if let Some(history) = self.lexicons.remove(lexicon) {
+            self.lexicons.insert(name.to_string(), history);
+        }
+        self.save()
+    }
+}
+
+/// Parses a time of day as `HH:MM` into minutes since midnight.
+pub fn parse_time(time: &str) -> Option<u64> {
+    let (hours, minutes) = time.split_once(':')?;
+    let hours = hours.parse::<u64>().ok()?;
+    let minutes = minutes.parse::<u64>().ok()?;
+    if hours >= 24 || minutes >= 60 {
+        return None;
+    }
+    Some(hours * 60 + minutes)
+}
+
+/// Picks the entries of the lexicons whose post is due today.
+fn due(lexicons: &[Slot], history: &mut History, day: u64, minute: u64) -> Vec<Post> {
+    let mut rng = rand::thread_rng();
+    let mut posts = Vec::new();
+    for slot in lexicons {
+        // a lexicon under edit is picked up again on the next minute
+        let Ok(lexicon) = slot.try_lock() else {
+            continue;
+        };
+        let Some(config) = &lexicon.word_of_the_day else {
+            continue;
+        };
+        if parse_time(&config.time)
+            .map(|time| minute < time)
+            .unwrap_or(true)
+        {
+            continue;
+        }
+        let lexicon_history = history.lexicons.entry(lexicon.name.clone()).or_default();
+        if lexicon_history.last_day == Some(day) {
+            continue;
+        }
+        let entries = || lexicon.words.values().flatten();
+        let mut entry = entries()
+            .filter(|(word, _)| !lexicon_history.posted.contains(*word))
+            .choose(&mut rng);
+        if entry.is_none() {
+            // every entry was posted, start over
+            lexicon_history.posted.clear();
+            entry = entries().choose(&mut rng);
+        }
+        let Some((word, description)) = entry else {
+            continue;
+        };
+        posts.push(Post {
+            lexicon: lexicon.name.clone(),
+            channel: config.channel,
+            word: word.clone(),
+            description: description.clone(),
+        });
+    }
+    posts
+}
+
+/// Posts the word of the day of every lexicon that has one configured.
+pub async fn run(http: Arc<Http>, lexicons: Arc<RwLock<Vec<Slot>>>, history: Arc<Mutex<History>>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        let day = now / 86400;
+        let minute = now % 86400 / 60;
+        let posts = due(
+            &lexicons.read().unwrap(),
+            &mut history.lock().unwrap(),
+            day,
+            minute,
+        );
+        if posts.is_empty() {
+            continue;
+        }
+        let mut sent_posts = Vec::with_capacity(posts.len());
+        for post in posts {
+            let sent = post
+                .channel
+                .send_message(&http, |message| {
+                    message.embed(|embed| {
+                        embed
+                            .title(format!("Word of the day: {}", post.word))
+                            .description(&post.description)
+                            .footer(|footer| footer.text(&post.lexicon))
+                            .color(Color::from_rgb(0x58, 0x65, 0xf2))
+                    })
+                })
+                .await;
+            if let Err(err) = &sent {
+                error!("Could not post word of the day: {err}");
+            }
+            sent_posts.push((post, sent.is_ok()));
+        }
+        // the history is looked up by the name the lexicon had when the post was picked, a rename
+        // in the meantime only costs the record of this post
+        let mut history = history.lock().unwrap();
+        for (post, sent) in sent_posts {
+            let lexicon_history = history.lexicons.entry(post.lexicon).or_default();
+            // a failed post is not retried the same day
+            lexicon_history.last_day = Some(day);
+            if sent {
+                lexicon_history.posted.insert(post.word);
+            }
+        }
+        if !history.save() {
+            error!("Could not save word of the day history");
+        }
+    }
+}
@@ -1,3 +1,112 @@
 pub mod emulate;
 pub mod lexicon;
 pub mod test;
+
+use serenity::{
+    async_trait,
+    builder::CreateApplicationCommand,
+    client::Context,
+    http::Http,
+    model::{
+        id::GuildId,
+        prelude::{
+            command::Command,
+            component::ActionRowComponent,
+            interaction::{
+                application_command::ApplicationCommandInteraction,
+                autocomplete::AutocompleteInteraction,
+                message_component::MessageComponentInteraction, modal::ModalSubmitInteraction,
+            },
+        },
+    },
+};
+
+use crate::{Handler, Modal, Response};
+
+/// A slash command together with the modals, components and autocompletions it handles.
+///
+/// Modals and components are routed back to the command whose name prefixes their custom id.
+#[async_trait]
+pub trait SlashCommand: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn register<'a>(
+        &self,
+        handler: &Handler,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand;
+
+    /// Registers the command once the bot is connected, globally by default.
+    async fn register_global(&self, handler: &Handler, http: &Http) -> serenity::Result<()> {
+        Command::create_global_application_command(http, |command| self.register(handler, command))
+            .await?;
+        Ok(())
+    }
+
+    /// Registers the command in a guild that became available, nothing by default.
+    async fn register_guild(
+        &self,
+        _handler: &Handler,
+        _http: &Http,
+        _guild_id: GuildId,
+    ) -> serenity::Result<()> {
+        Ok(())
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Response;
+
+    async fn modal(
+        &self,
+        _handler: &Handler,
+        _modal: Modal,
+        _submission: &mut ModalSubmitInteraction,
+    ) -> Response {
+        Response::unimplemented()
+    }
+
+    async fn component(
+        &self,
+        _handler: &Handler,
+        _component: &MessageComponentInteraction,
+    ) -> Response {
+        Response::unimplemented()
+    }
+
+    /// Suggests values for the focused option.
+    async fn autocomplete(
+        &self,
+        _handler: &Handler,
+        _autocomplete: &AutocompleteInteraction,
+    ) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Every command of the bot.
+pub fn registry() -> Vec<Box<dyn SlashCommand>> {
+    vec![
+        Box::new(lexicon::LexiconCommand),
+        Box::new(lexicon::admin::LexiconAdminCommand),
+        Box::new(emulate::EmulateCommand),
+        Box::new(test::TestCommand),
+    ]
+}
+
+/// The values of the text inputs of a submitted modal in order.
+pub fn text_inputs(submission: &mut ModalSubmitInteraction) -> Vec<String> {
+    submission
+        .data
+        .components
+        .drain(..)
+        .flat_map(|row| row.components)
+        .filter_map(|component| match component {
+            ActionRowComponent::InputText(input) => Some(input.value),
+            _ => None,
+        })
+        .collect()
+}
@@ -1,12 +1,18 @@
 use serenity::{
+    async_trait,
     builder::CreateApplicationCommand,
+    client::Context,
     model::prelude::{
-        component::{ActionRowComponent, InputTextStyle},
-        interaction::{application_command::CommandDataOption, modal::ModalSubmitInteraction},
+        component::InputTextStyle,
+        interaction::{
+            application_command::{ApplicationCommandInteraction, CommandDataOption},
+            modal::ModalSubmitInteraction,
+        },
     },
 };
 
-use crate::{Modal, Response};
+use super::{text_inputs, SlashCommand};
+use crate::{Handler, Modal, Response};
 
 pub fn run(_options: &[CommandDataOption]) -> Response {
     Response::modal(
@@ -25,24 +31,52 @@ pub fn run(_options: &[CommandDataOption]) -> Response {
                 })
             });
         },
-        Modal::Test,
+        Modal::new("test", "input", ""),
     )
 }
 
 pub async fn handle_modal(submission: &mut ModalSubmitInteraction) -> Response {
-    let ActionRowComponent::InputText(input) = submission
-        .data
-        .components
-        .drain(..)
-        .next()
-        .unwrap()
-        .components
-        .drain(..)
-        .next()
-        .unwrap() else {unimplemented!()};
-    Response::success("Test response", input.value)
+    let Ok([input]) = <[String; 1]>::try_from(text_inputs(submission)) else {
+        return Response::invalid_command();
+    };
+    Response::success("Test response", input)
 }
 
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
     command.name("test").description("Tests the bot")
 }
+
+pub struct TestCommand;
+
+#[async_trait]
+impl SlashCommand for TestCommand {
+    fn name(&self) -> &'static str {
+        "test"
+    }
+
+    fn register<'a>(
+        &self,
+        _handler: &Handler,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        register(command)
+    }
+
+    async fn run(
+        &self,
+        _handler: &Handler,
+        _ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Response {
+        run(&command.data.options)
+    }
+
+    async fn modal(
+        &self,
+        _handler: &Handler,
+        _modal: Modal,
+        submission: &mut ModalSubmitInteraction,
+    ) -> Response {
+        handle_modal(submission).await
+    }
+}
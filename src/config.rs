@@ -0,0 +1,384 @@
+use std::{fs, io, path::Path};
+
+use toml::{value::Table, Value};
+
+use crate::{error::Error, Config};
+
+/// Prefix of the environment variables overriding config fields.
+///
+/// The rest of the name is the path to the field with `__` between the keys, like
+/// `SQYYY_BOT__GIT__PASSWORD` or `SQYYY_BOT__LEXICONS__0__TARGET_FILE`. Values are parsed as TOML
+/// and taken as a string if that fails, so strings that look like numbers need quotes. Secrets
+/// are always taken as a string.
+pub const ENV_PREFIX: &str = "SQYYY_BOT__";
+
+/// Fields whose value may be read from a file named by the field with a `-file` suffix.
+const SECRETS: [&[&str]; 2] = [&["discord", "token"], &["git", "password"]];
+
+/// Values that replaced the ones from the config file, restored when the config is saved so
+/// neither overrides nor secrets end up in the file.
+#[derive(Default)]
+pub struct Overrides {
+    original: Vec<Original>,
+}
+
+/// A replaced field and its value in the file.
+struct Original {
+    /// The lexicon the field belongs to, as lexicons move in the array when one is deleted.
+    lexicon: Option<String>,
+    /// The path of the field, relative to the lexicon if there is one.
+    field: Vec<String>,
+    value: Option<Value>,
+}
+
+/// Reads the config file and applies the overrides from the given environment variables.
+///
+/// The file may be missing if the environment overrides something.
+pub fn load(
+    path: &Path,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<(Config, Overrides), Error> {
+    let vars = vars
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.strip_prefix(ENV_PREFIX)?.to_string(), value)))
+        .collect::<Vec<_>>();
+    let mut value = match fs::read_to_string(path) {
+        Ok(source) => toml::from_str(&source).map_err(|err| Error::file(path, err))?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound && !vars.is_empty() => {
+            Value::Table(Table::new())
+        }
+        Err(err) => return Err(Error::file(path, err)),
+    };
+    let mut overrides = Overrides::default();
+    for (name, raw) in vars {
+        let field = name
+            .split("__")
+            .map(|key| key.to_lowercase().replace('_', "-"))
+            .collect::<Vec<_>>();
+        // a secret like `123` or `true` must not turn into a number or a boolean
+        let parsed = if secret(&field) {
+            Value::String(raw)
+        } else {
+            parse(&raw)
+        };
+        overrides
+            .replace(&mut value, field, parsed)
+            .map_err(|err| Error::Config(format!("{ENV_PREFIX}{name}: {err}")))?;
+    }
+    let mut secrets = SECRETS
+        .iter()
+        .map(|field| field.iter().map(|key| key.to_string()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    if let Some(Value::Table(repositories)) = get(&value, &["repositories".to_string()]) {
+        for name in repositories.keys() {
+            secrets.push(vec![
+                "repositories".to_string(),
+                name.clone(),
+                "password".to_string(),
+            ]);
+        }
+    }
+    for field in secrets {
+        let mut file_field = field.clone();
+        if let Some(key) = file_field.last_mut() {
+            key.push_str("-file");
+        }
+        let Some(file) = get(&value, &file_field) else {
+            continue;
+        };
+        let Some(file) = file.as_str() else {
+            return Err(Error::Config(format!(
+                "{} is not a path",
+                file_field.join(".")
+            )));
+        };
+        let secret = fs::read_to_string(file).map_err(|err| Error::file(file, err))?;
+        // files written by editors or `echo` end with a newline
+        let secret = Value::String(secret.trim_end_matches(['\r', '\n']).to_string());
+        overrides
+            .replace(&mut value, field, secret)
+            .map_err(Error::Config)?;
+    }
+    overrides.key_lexicons(&value);
+    let config = value.try_into().map_err(|err| Error::file(path, err))?;
+    Ok((config, overrides))
+}
+
+/// Whether a field holds a secret, which is always a string.
+fn secret(field: &[String]) -> bool {
+    let field = field.iter().map(String::as_str).collect::<Vec<_>>();
+    SECRETS.iter().any(|secret| *secret == field.as_slice())
+        || matches!(field[..], ["repositories", _, "password"])
+}
+
+/// Parses a value from the environment as TOML, or takes it as a string.
+fn parse(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {raw}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+/// Serializes the config as it is written to the config file.
+pub fn save(config: &Config, overrides: &Overrides) -> Result<String, String> {
+    let mut value = Value::try_from(config).map_err(|err| err.to_string())?;
+    // restore in reverse so a field overridden twice ends up with the value from the file
+    for original in overrides.original.iter().rev() {
+        let field = match &original.lexicon {
+            Some(name) => {
+                let Some(index) = lexicon_index(&value, name) else {
+                    continue;
+                };
+                let mut field = vec!["lexicons".to_string(), index.to_string()];
+                field.extend(original.field.iter().cloned());
+                field
+            }
+            None => original.field.clone(),
+        };
+        // only fields that still exist are restored, nothing is created
+        let _ = set(&mut value, &field, original.value.clone(), false);
+    }
+    toml::to_string(&value).map_err(|err| err.to_string())
+}
+
+impl Overrides {
+    fn replace(&mut self, value: &mut Value, field: Vec<String>, new: Value) -> Result<(), String> {
+        let original = get(value, &field).cloned();
+        set(value, &field, Some(new), true)?;
+        self.original.push(Original {
+            lexicon: None,
+            field,
+            value: original,
+        });
+        Ok(())
+    }
+
+    /// Ties the overridden fields of lexicons to the names of the lexicons once every override
+    /// is applied.
+    fn key_lexicons(&mut self, value: &Value) {
+        for original in &mut self.original {
+            let [lexicons, index, ..] = original.field.as_slice() else {
+                continue;
+            };
+            if lexicons != "lexicons" {
+                continue;
+            }
+            let name = get(
+                value,
+                &[lexicons.clone(), index.clone(), "name".to_string()],
+            );
+            let Some(name) = name.and_then(Value::as_str) else {
+                continue;
+            };
+            original.lexicon = Some(name.to_string());
+            original.field.drain(..2);
+        }
+    }
+
+    /// Follows a lexicon renamed at runtime, whose new name is not overridden anymore.
+    pub fn rename_lexicon(&mut self, name: &str, new_name: &str) {
+        self.original.retain(|original| {
+            original.lexicon.as_deref() != Some(name) || original.field != ["name"]
+        });
+        for original in &mut self.original {
+            if original.lexicon.as_deref() == Some(name) {
+                original.lexicon = Some(new_name.to_string());
+            }
+        }
+    }
+
+    /// Forgets the overrides of a lexicon deleted at runtime.
+    pub fn remove_lexicon(&mut self, name: &str) {
+        self.original
+            .retain(|original| original.lexicon.as_deref() != Some(name));
+    }
+}
+
+fn lexicon_index(value: &Value, name: &str) -> Option<usize> {
+    let Some(Value::Array(lexicons)) = get(value, &["lexicons".to_string()]) else {
+        return None;
+    };
+    lexicons
+        .iter()
+        .position(|lexicon| lexicon.get("name").and_then(Value::as_str) == Some(name))
+}
+
+fn get<'a>(value: &'a Value, field: &[String]) -> Option<&'a Value> {
+    field.iter().try_fold(value, |value, key| match value {
+        Value::Table(table) => table.get(key),
+        Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// Sets or, given `None`, removes a field.
+///
+/// Array elements are addressed by index. With `create`, missing tables are created on the way
+/// and the index after the last element appends to an array, otherwise these are errors.
+fn set(
+    value: &mut Value,
+    field: &[String],
+    new: Option<Value>,
+    create: bool,
+) -> Result<(), String> {
+    let Some((key, parents)) = field.split_last() else {
+        return Err("The field is empty".to_string());
+    };
+    let mut value = value;
+    for parent in parents {
+        value = match value {
+            Value::Table(table) if create => table
+                .entry(parent.clone())
+                .or_insert_with(|| Value::Table(Table::new())),
+            Value::Table(table) => table
+                .get_mut(parent)
+                .ok_or_else(|| format!("{parent} does not exist"))?,
+            Value::Array(array) => {
+                let index = index(parent, array.len(), create)?;
+                if index == array.len() {
+                    array.push(Value::Table(Table::new()));
+                }
+                &mut array[index]
+            }
+            _ => return Err(format!("{parent} is not a table")),
+        };
+    }
+    match (value, new) {
+        (Value::Table(table), Some(new)) => {
+            table.insert(key.clone(), new);
+        }
+        (Value::Table(table), None) => {
+            table.remove(key);
+        }
+        (Value::Array(array), Some(new)) => {
+            let index = index(key, array.len(), create)?;
+            if index == array.len() {
+                array.push(new);
+            } else {
+                array[index] = new;
+            }
+        }
+        (Value::Array(array), None) => {
+            let index = index(key, array.len(), false)?;
+            array.remove(index);
+        }
+        _ => return Err(format!("{} is not a table", parents.join("."))),
+    }
+    Ok(())
+}
+
+/// Parses an array index, which may be `len` to append if `append` is set.
+fn index(key: &str, len: usize, append: bool) -> Result<usize, String> {
+    match key.parse::<usize>() {
+        Ok(index) if index < len || append && index == len => Ok(index),
+        _ => Err(format!("{key} is not an index of the {len} elements")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[discord]
+token = "token"
+
+[git]
+username = "bot"
+email = "bot@example.com"
+password = "secret"
+url = "https://example.com/lexicons.git"
+path = "repository"
+
+[[lexicons]]
+name = "words"
+file = "words.ron"
+target-file = "words.md"
+"#;
+
+    fn field(field: &str) -> Vec<String> {
+        field.split('.').map(str::to_string).collect()
+    }
+
+    /// Loads the config from a file of its own, with the given variables set.
+    fn load_with(name: &str, vars: &[(&str, &str)]) -> (Config, Overrides) {
+        let path =
+            std::env::temp_dir().join(format!("sqyyy-bot-{}-{name}.toml", std::process::id()));
+        fs::write(&path, CONFIG).unwrap();
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (format!("{ENV_PREFIX}{name}"), value.to_string()));
+        let loaded = load(&path, vars);
+        fs::remove_file(&path).unwrap();
+        loaded.unwrap()
+    }
+
+    fn saved(config: &Config, overrides: &Overrides) -> Value {
+        toml::from_str(&save(config, overrides).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parses_values_as_toml_or_string() {
+        assert_eq!(parse("42"), Value::Integer(42));
+        assert_eq!(parse("true"), Value::Boolean(true));
+        assert_eq!(parse("\"42\""), Value::String("42".to_string()));
+        assert_eq!(parse("words.md"), Value::String("words.md".to_string()));
+    }
+
+    #[test]
+    fn recognizes_secrets() {
+        assert!(secret(&field("discord.token")));
+        assert!(secret(&field("git.password")));
+        assert!(secret(&field("repositories.archive.password")));
+        assert!(!secret(&field("git.url")));
+        assert!(!secret(&field("repositories.archive.url")));
+    }
+
+    #[test]
+    fn sets_fields() {
+        let mut value = Value::Table(Table::new());
+        assert!(set(&mut value, &field("git.url"), Some(parse("url")), false).is_err());
+        set(&mut value, &field("git.url"), Some(parse("url")), true).unwrap();
+        assert_eq!(get(&value, &field("git.url")), Some(&parse("url")));
+        set(&mut value, &field("git.url"), None, false).unwrap();
+        assert_eq!(get(&value, &field("git.url")), None);
+    }
+
+    #[test]
+    fn appends_to_arrays_only_when_creating() {
+        let mut value = toml::from_str::<Value>("list = [1]").unwrap();
+        assert!(set(&mut value, &field("list.1"), Some(parse("2")), false).is_err());
+        assert!(set(&mut value, &field("list.2"), Some(parse("3")), true).is_err());
+        set(&mut value, &field("list.1"), Some(parse("2")), true).unwrap();
+        assert_eq!(value["list"], parse("[1, 2]"));
+    }
+
+    #[test]
+    fn restores_overridden_fields_when_saving() {
+        let (config, overrides) = load_with(
+            "restore",
+            &[
+                ("GIT__PASSWORD", "1234"),
+                ("LEXICONS__0__FILE", "other.ron"),
+            ],
+        );
+        assert_eq!(config.git.password, "1234");
+        assert_eq!(config.lexicons[0].file, Path::new("other.ron"));
+        let value = saved(&config, &overrides);
+        assert_eq!(value["git"]["password"].as_str(), Some("secret"));
+        assert_eq!(value["lexicons"][0]["file"].as_str(), Some("words.ron"));
+    }
+
+    #[test]
+    fn follows_renamed_and_removed_lexicons() {
+        let (mut config, mut overrides) =
+            load_with("rename", &[("LEXICONS__0__FILE", "other.ron")]);
+        config.lexicons[0].name = "renamed".to_string();
+        overrides.rename_lexicon("words", "renamed");
+        let value = saved(&config, &overrides);
+        assert_eq!(value["lexicons"][0]["file"].as_str(), Some("words.ron"));
+        overrides.remove_lexicon("renamed");
+        let value = saved(&config, &overrides);
+        assert_eq!(value["lexicons"][0]["file"].as_str(), Some("other.ron"));
+    }
+}
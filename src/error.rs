@@ -0,0 +1,53 @@
+use std::{fmt, path::PathBuf};
+
+/// A problem that keeps the bot or a part of it from working.
+#[derive(Debug)]
+pub enum Error {
+    /// The configuration is invalid.
+    Config(String),
+    /// A file could not be read, parsed or written.
+    File {
+        path: PathBuf,
+        reason: String,
+    },
+    /// A repository could not be set up.
+    Git {
+        repository: String,
+        reason: String,
+    },
+    /// A lexicon could not be loaded or rendered.
+    Lexicon {
+        name: String,
+        reason: String,
+    },
+    Discord(serenity::Error),
+}
+
+impl Error {
+    pub fn file(path: impl Into<PathBuf>, reason: impl fmt::Display) -> Self {
+        Self::File {
+            path: path.into(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(reason) => write!(f, "Invalid configuration: {reason}"),
+            Error::File { path, reason } => write!(f, "{}: {reason}", path.display()),
+            Error::Git { repository, reason } => write!(f, "Repository {repository}: {reason}"),
+            Error::Lexicon { name, reason } => write!(f, "Lexicon {name}: {reason}"),
+            Error::Discord(err) => write!(f, "Discord: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serenity::Error> for Error {
+    fn from(err: serenity::Error) -> Self {
+        Self::Discord(err)
+    }
+}
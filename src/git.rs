@@ -1,82 +1,415 @@
 use std::{
     fs,
-    process::{exit, Command, Stdio},
+    io::Read,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-use crate::GitConfig;
+use tracing::{debug, error, warn};
 
-fn run(config: &GitConfig, args: &[&str]) -> bool {
-    Command::new("git")
+use crate::{error::Error, metrics, GitConfig, SigningFormat};
+
+/// A working copy managed by the bot.
+pub struct Repository {
+    pub name: String,
+    pub config: GitConfig,
+    lock: Mutex<()>,
+    /// Whether the last exchange with the remote succeeded.
+    synced: AtomicBool,
+}
+
+impl Repository {
+    pub fn new(name: String, config: GitConfig) -> Self {
+        Self {
+            name,
+            config,
+            lock: Mutex::new(()),
+            synced: AtomicBool::new(true),
+        }
+    }
+
+    /// Locks the working copy for a sequence of git operations.
+    pub fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap()
+    }
+
+    pub fn synced(&self) -> bool {
+        self.synced.load(Ordering::Relaxed)
+    }
+
+    pub fn set_synced(&self, synced: bool) {
+        self.synced.store(synced, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failure,
+    /// The command ran longer than the configured timeout and was killed.
+    TimedOut,
+}
+
+fn execute(config: &GitConfig, args: &[&str]) -> Status {
+    execute_captured(config, args).0
+}
+
+/// Runs git and returns its status together with what it wrote to stderr.
+fn execute_captured(config: &GitConfig, args: &[&str]) -> (Status, String) {
+    let command = args.first().copied().unwrap_or_default();
+    let start = Instant::now();
+    let child = Command::new("git")
         .stdout(Stdio::null())
-        .stderr(Stdio::null())
+        .stderr(Stdio::piped())
         .args(args)
         .current_dir(&config.path)
-        .status()
-        .expect("Execute git command")
-        .success()
+        // fail instead of waiting for credentials nobody can enter
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            error!(command, "Could not execute git: {err}");
+            return (Status::Failure, err.to_string());
+        }
+    };
+    // read concurrently so a full pipe cannot block git
+    let stderr = child.stderr.take();
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut output);
+        }
+        output
+    });
+    let deadline = start + Duration::from_secs(config.timeout);
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break Status::Success,
+            Ok(Some(_)) => break Status::Failure,
+            Ok(None) => {}
+            Err(err) => {
+                error!(command, "Could not wait for git: {err}");
+                let _ = child.kill();
+                let _ = child.wait();
+                break Status::Failure;
+            }
+        }
+        if config.timeout > 0 && Instant::now() >= deadline {
+            // the process is gone if killing fails, so the errors can be ignored
+            let _ = child.kill();
+            let _ = child.wait();
+            break Status::TimedOut;
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    let stderr = redact(config, reader.join().unwrap_or_default().trim());
+    let elapsed = start.elapsed();
+    metrics::git(command, elapsed, status);
+    let elapsed_ms = elapsed.as_millis() as u64;
+    match status {
+        Status::Success => debug!(command, elapsed_ms, stderr, "git command succeeded"),
+        Status::Failure if stderr.is_empty() => debug!(command, elapsed_ms, "git command failed"),
+        Status::Failure => warn!(command, elapsed_ms, stderr, "git command failed"),
+        Status::TimedOut => warn!(command, elapsed_ms, stderr, "git command timed out"),
+    }
+    (status, stderr)
 }
 
-pub fn setup(config: &GitConfig) {
-    if let Err(err) = fs::create_dir_all(&config.path) {
-        eprintln!("Could not create repository directory: {err}");
-        exit(1);
+/// Removes the password from git output, which may contain the remote URL.
+fn redact(config: &GitConfig, output: &str) -> String {
+    if config.password.is_empty() {
+        return output.to_string();
     }
-    if !run(config, &["status"]) {
-        clone(config);
-        login(config);
+    output.replace(&config.password, "***")
+}
+
+fn run(config: &GitConfig, args: &[&str]) -> bool {
+    execute(config, args) == Status::Success
+}
+
+fn output(config: &GitConfig, args: &[&str]) -> Option<String> {
+    let command = args.first().copied().unwrap_or_default();
+    let output = match Command::new("git")
+        .args(args)
+        .current_dir(&config.path)
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) => {
+            error!(command, "Could not execute git: {err}");
+            return None;
+        }
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        warn!(command, stderr = %redact(config, stderr.trim()), "git command failed");
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Clones or opens the working copy of a repository and configures the git user.
+pub fn setup(repository: &Repository) -> Result<(), Error> {
+    let config = &repository.config;
+    let error = |reason: String| Error::Git {
+        repository: repository.name.clone(),
+        reason,
+    };
+    fs::create_dir_all(&config.path)
+        .map_err(|err| error(format!("Could not create repository directory: {err}")))?;
+    if !run(config, &["status"]) && !clone(config) {
+        if !config.offline_tolerant {
+            return Err(error("Could not clone git repository".to_string()));
+        }
+        warn!("Could not clone git repository, starting with an empty repository");
+        init(config).map_err(error)?;
     }
     if !run(config, &["status"]) {
-        eprintln!("Could not setup git repository");
-        exit(1);
+        return Err(error("Could not setup git repository".to_string()));
     }
+    login(config).map_err(error)?;
+    if !checkout(config) && !config.offline_tolerant {
+        return Err(error("Could not check out branch".to_string()));
+    }
+    Ok(())
 }
 
-fn clone(config: &GitConfig) {
-    if !run(
-        config,
-        &[
-            "clone",
-            &format!(
-                "https://{}:{}@{}",
-                config.username,
-                config.password,
-                config.url.trim_start_matches("https://")
-            ),
-            ".",
-        ],
-    ) {
-        eprintln!("Could not clone git repository");
-        exit(1);
+/// Whether the remote can be reached with the configured credentials.
+pub fn reachable(config: &GitConfig) -> bool {
+    probe(config).is_ok()
+}
+
+/// Tries to reach the remote, failing with the reason reported by git.
+pub fn probe(config: &GitConfig) -> Result<(), String> {
+    let mut config = config.clone();
+    // the working copy may not exist yet, which does not matter as the remote is given explicitly
+    if !config.path.is_dir() {
+        config.path = PathBuf::from(".");
+    }
+    match execute_captured(&config, &["ls-remote", "--heads", &remote_url(&config)]) {
+        (Status::Success, _) => Ok(()),
+        (Status::TimedOut, _) => Err("git timed out".to_string()),
+        (Status::Failure, stderr) if stderr.is_empty() => Err("git failed".to_string()),
+        (Status::Failure, stderr) => Err(stderr),
+    }
+}
+
+fn remote_url(config: &GitConfig) -> String {
+    format!(
+        "https://{}:{}@{}",
+        config.username,
+        config.password,
+        config.url.trim_start_matches("https://")
+    )
+}
+
+fn clone(config: &GitConfig) -> bool {
+    let url = remote_url(config);
+    let mut args = vec!["clone", &url, "."];
+    if let Some(branch) = &config.branch {
+        args.extend(["--branch", branch]);
     }
+    run(config, &args)
 }
 
-fn login(config: &GitConfig) {
+fn init(config: &GitConfig) -> Result<(), String> {
+    if !run(config, &["init"]) {
+        return Err("Could not initialize git repository".to_string());
+    }
+    if !run(config, &["remote", "add", "origin", &remote_url(config)]) {
+        return Err("Could not add git remote".to_string());
+    }
+    if let Some(branch) = &config.branch {
+        if !run(config, &["checkout", "-b", branch]) {
+            return Err(format!("Could not create branch {branch}"));
+        }
+    }
+    Ok(())
+}
+
+fn login(config: &GitConfig) -> Result<(), String> {
     if !run(config, &["config", "user.name", &config.username]) {
-        eprintln!("Could not set git username");
-        exit(1);
+        return Err("Could not set git username".to_string());
     }
     if !run(config, &["config", "user.email", &config.email]) {
-        eprintln!("Could not set git email");
-        exit(1);
+        return Err("Could not set git email".to_string());
+    }
+    let Some(signing) = &config.signing else {
+        if !run(config, &["config", "commit.gpgsign", "false"]) {
+            return Err("Could not disable GPG signing".to_string());
+        }
+        return Ok(());
+    };
+    let format = match signing.format {
+        SigningFormat::Openpgp => "openpgp",
+        SigningFormat::Ssh => "ssh",
+    };
+    if !run(config, &["config", "gpg.format", format]) {
+        return Err("Could not set signing format".to_string());
     }
-    if !run(config, &["config", "commit.gpgsign", "false"]) {
-        eprintln!("Could not disable GPG signing");
-        exit(1);
+    if !run(config, &["config", "user.signingkey", &signing.key]) {
+        return Err("Could not set signing key".to_string());
     }
+    if !run(config, &["config", "commit.gpgsign", "true"]) {
+        return Err("Could not enable commit signing".to_string());
+    }
+    Ok(())
+}
+
+/// Checks out the configured branch and makes it track the remote branch of the same name.
+///
+/// Without a configured branch, only a current branch without upstream gets tracked.
+fn checkout(config: &GitConfig) -> bool {
+    let branch = match &config.branch {
+        Some(branch) => branch.clone(),
+        None => {
+            if run(config, &["rev-parse", "--abbrev-ref", "@{upstream}"]) {
+                return true;
+            }
+            let Some(branch) = output(config, &["symbolic-ref", "--short", "HEAD"]) else {
+                error!("Could not determine current branch");
+                return false;
+            };
+            branch
+        }
+    };
+    let branch = branch.as_str();
+    if !run(config, &["fetch", "origin"]) {
+        error!("Could not fetch git repository");
+        return false;
+    }
+    let remote = format!("origin/{branch}");
+    let has_local = run(
+        config,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{branch}"),
+        ],
+    );
+    let has_remote = run(
+        config,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/remotes/{remote}"),
+        ],
+    );
+    let checked_out = match (has_local, has_remote) {
+        (true, _) => run(config, &["checkout", branch]),
+        (false, true) => run(config, &["checkout", "-b", branch, "--track", &remote]),
+        (false, false) => run(config, &["checkout", "-b", branch]),
+    };
+    if !checked_out {
+        error!("Could not check out branch {branch}");
+        return false;
+    }
+    let tracked = if has_remote {
+        run(config, &["branch", "--set-upstream-to", &remote, branch])
+    } else {
+        run(config, &["push", "--set-upstream", "origin", branch])
+    };
+    if !tracked {
+        error!("Could not track remote branch {remote}");
+        return false;
+    }
+    true
 }
 
 pub fn stage(config: &GitConfig, what: &str) -> bool {
     run(config, &["add", what])
 }
 
-pub fn commit(config: &GitConfig, message: &str) -> bool {
-    run(config, &["commit", "-m", message])
+pub fn commit(config: &GitConfig, message: &str, author: Option<&str>) -> Status {
+    match author {
+        Some(author) => execute(config, &["commit", "-m", message, "--author", author]),
+        None => execute(config, &["commit", "-m", message]),
+    }
+}
+
+/// The commit currently checked out, `None` if there is none yet.
+pub fn head(config: &GitConfig) -> Option<String> {
+    output(config, &["rev-parse", "HEAD"])
 }
 
-pub fn pull(config: &GitConfig) -> bool {
-    run(config, &["pull"])
+/// Discards every change and commit after `commit`.
+pub fn reset(config: &GitConfig, commit: &str) -> bool {
+    run(config, &["reset", "--hard", commit])
 }
 
-pub fn push(config: &GitConfig) -> bool {
-    run(config, &["push"])
+pub fn pull(config: &GitConfig) -> Status {
+    merge(config, &["pull"])
+}
+
+/// Runs a command merging remote changes and aborts the merge if it fails, so a conflict never
+/// leaves the working copy mid-merge.
+fn merge(config: &GitConfig, args: &[&str]) -> Status {
+    let status = execute(config, args);
+    let merging = config.path.join(".git").join("MERGE_HEAD").exists();
+    if status != Status::Success && merging && !run(config, &["merge", "--abort"]) {
+        error!("Could not abort merge");
+    }
+    status
+}
+
+pub fn push(config: &GitConfig) -> Status {
+    execute(config, &["push"])
+}
+
+fn outbox_path(config: &GitConfig) -> PathBuf {
+    config.path.join(".git").join("outbox.ron")
+}
+
+/// Returns the summaries of the commits that are waiting to be pushed.
+pub fn outbox(config: &GitConfig) -> Vec<String> {
+    let Ok(outbox) = fs::read_to_string(outbox_path(config)) else {
+        return Vec::new();
+    };
+    ron::from_str(&outbox).unwrap_or_default()
+}
+
+/// Records a commit that could not be pushed.
+pub fn queue(config: &GitConfig, summary: &str) -> bool {
+    let mut outbox = outbox(config);
+    outbox.push(summary.to_string());
+    let Ok(outbox) = ron::to_string(&outbox) else {
+        return false;
+    };
+    fs::write(outbox_path(config), outbox).is_ok()
+}
+
+pub fn clear_outbox(config: &GitConfig) {
+    let path = outbox_path(config);
+    if path.exists() && fs::remove_file(path).is_err() {
+        error!("Could not clear git outbox");
+    }
+}
+
+/// Merges the remote changes and pushes the commits from the outbox.
+pub fn flush(config: &GitConfig) -> Status {
+    // the repository may have been initialized without the remote being reachable
+    if !checkout(config) {
+        return Status::Failure;
+    }
+    let pulled = merge(
+        config,
+        &["pull", "--no-rebase", "--allow-unrelated-histories"],
+    );
+    if pulled != Status::Success {
+        return pulled;
+    }
+    let pushed = push(config);
+    if pushed == Status::Success {
+        clear_outbox(config);
+    }
+    pushed
 }
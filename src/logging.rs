@@ -0,0 +1,40 @@
+use std::{fs::OpenOptions, io, sync::Mutex};
+
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, EnvFilter};
+
+use crate::{error::Error, LogConfig, LogFormat};
+
+/// Installs the global subscriber.
+///
+/// The level given on the command line takes precedence over `RUST_LOG`, which takes precedence
+/// over the configured level.
+pub fn init(config: &LogConfig, level: Option<&str>) -> Result<(), Error> {
+    let filter = match (level, EnvFilter::try_from_default_env()) {
+        (Some(level), _) => EnvFilter::try_new(level),
+        (None, Ok(filter)) => Ok(filter),
+        (None, Err(_)) => EnvFilter::try_new(&config.level),
+    }
+    .map_err(|err| Error::Config(format!("Invalid log level: {err}")))?;
+    let writer = match &config.file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|err| Error::file(path, err))?;
+            BoxMakeWriter::new(Mutex::new(file))
+        }
+        None => BoxMakeWriter::new(io::stdout),
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        // colors only make sense in a terminal
+        .with_ansi(config.file.is_none());
+    match config.format {
+        LogFormat::Full => subscriber.init(),
+        LogFormat::Compact => subscriber.compact().init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+    Ok(())
+}
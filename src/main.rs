@@ -1,144 +1,891 @@
+mod check;
+mod cli;
+mod collation;
 mod commands;
+mod config;
+mod error;
 mod git;
+mod logging;
+mod metrics;
+mod modals;
 mod parser;
+mod persist;
+mod render;
+mod split;
+mod suggestions;
+mod template;
+mod validation;
 
-use std::{collections::HashMap, fs, mem, path::PathBuf, process::exit, sync::Mutex};
+use std::{
+    collections::{BTreeMap, HashSet},
+    env, fs,
+    net::SocketAddr,
+    path::PathBuf,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Instant,
+};
 
-use commands::lexicon::{create_add_modal, create_update_modal, load, Lexicon};
-use git::setup;
-use serde::Deserialize;
+use commands::{
+    lexicon::{
+        create_add_modal, create_update_modal, load,
+        quiz::{Quizzes, Scores},
+        stats::ChangeLog,
+        word_of_the_day::History as WordOfTheDayHistory,
+        Slot,
+    },
+    SlashCommand,
+};
+use error::Error;
+use git::{setup, Repository};
+use modals::Modals;
+use serde::{Deserialize, Serialize};
 use serenity::{
     async_trait,
-    builder::{CreateComponents, CreateInteractionResponse, CreateInteractionResponseData},
+    builder::{
+        CreateComponents, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseData,
+        CreateInteractionResponseFollowup, EditInteractionResponse,
+    },
+    client::bridge::gateway::event::ShardStageUpdateEvent,
+    gateway::ConnectionStage,
+    http::Http,
+    json::{hashmap_to_json_map, Value},
     model::prelude::{
-        command::Command,
-        interaction::{Interaction, InteractionResponseType},
+        component::ButtonStyle,
+        interaction::{
+            application_command::CommandDataOption, Interaction, InteractionResponseType,
+        },
         *,
     },
     prelude::*,
     utils::Color,
     Client,
 };
+use suggestions::Suggestions;
+use tracing::{debug, error, field, info, info_span, Instrument, Span};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Config {
     discord: DiscordConfig,
+    /// The default repository.
     git: GitConfig,
+    /// Additional repositories by name.
+    #[serde(default)]
+    repositories: BTreeMap<String, GitConfig>,
     lexicons: Vec<LexiconConfig>,
+    /// File the suggestions waiting for review are stored in.
+    #[serde(default = "default_suggestions_file")]
+    suggestions_file: PathBuf,
+    /// File the posted words of the day are stored in.
+    #[serde(default = "default_word_of_the_day_file")]
+    word_of_the_day_file: PathBuf,
+    /// File the quiz scores are stored in.
+    #[serde(default = "default_quiz_scores_file")]
+    quiz_scores_file: PathBuf,
+    /// File the published changes are recorded in for the statistics.
+    #[serde(default = "default_change_log_file")]
+    change_log_file: PathBuf,
+    #[serde(default)]
+    modals: ModalConfig,
+    #[serde(default)]
+    log: LogConfig,
+    /// Local HTTP endpoint serving metrics and health checks, disabled if absent.
+    #[serde(default)]
+    metrics: Option<MetricsConfig>,
+}
+
+fn default_suggestions_file() -> PathBuf {
+    PathBuf::from(".suggestions.ron")
+}
+
+fn default_word_of_the_day_file() -> PathBuf {
+    PathBuf::from(".word-of-the-day.ron")
+}
+
+fn default_quiz_scores_file() -> PathBuf {
+    PathBuf::from(".quiz-scores.ron")
+}
+
+fn default_change_log_file() -> PathBuf {
+    PathBuf::from(".changes.ron")
+}
+
+/// How long opened modals stay valid and where they are kept.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ModalConfig {
+    /// Seconds until an opened modal expires.
+    ttl: u64,
+    /// File the pending modals are persisted in across restarts, kept in memory only if absent.
+    file: Option<PathBuf>,
+}
+
+impl Default for ModalConfig {
+    fn default() -> Self {
+        Self {
+            ttl: 900,
+            file: None,
+        }
+    }
+}
+
+/// Where and how the bot logs.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct LogConfig {
+    /// Filter directives like `info` or `sqyyy_bot=debug`, `RUST_LOG` takes precedence.
+    level: String,
+    format: LogFormat,
+    /// File the logs are appended to, standard output if absent.
+    file: Option<PathBuf>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "warn,sqyyy_bot=info".to_string(),
+            format: LogFormat::Full,
+            file: None,
+        }
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetricsConfig {
+    /// Address the endpoint listens on, like `127.0.0.1:9100`.
+    address: SocketAddr,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// Human readable lines including the fields of the enclosing spans.
+    #[default]
+    Full,
+    Compact,
+    /// One JSON object per line.
+    Json,
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct DiscordConfig {
     token: String,
+    /// File the token is read from instead.
+    #[serde(default)]
+    token_file: Option<PathBuf>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct GitConfig {
     username: String,
     email: String,
     password: String,
+    /// File the password is read from instead.
+    #[serde(default)]
+    password_file: Option<PathBuf>,
     url: String,
     path: PathBuf,
+    /// Branch to work on instead of the remote's default branch.
+    #[serde(default)]
+    branch: Option<String>,
+    /// Key to sign commits with, commits are unsigned if absent.
+    #[serde(default)]
+    signing: Option<SigningConfig>,
+    /// Keep working on the local working copy while the remote is unreachable.
+    #[serde(default)]
+    offline_tolerant: bool,
+    /// Interval in seconds between pulls of the repository, `0` disables syncing except for
+    /// pushing the pending changes of an offline tolerant repository.
+    #[serde(default = "default_sync_interval")]
+    sync_interval: u64,
+    /// Seconds a git command may run before it is aborted, `0` disables the limit.
+    #[serde(default = "default_git_timeout")]
+    timeout: u64,
+    #[serde(default)]
+    commit: CommitConfig,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SigningConfig {
+    format: SigningFormat,
+    /// The key as understood by git's `user.signingkey`, e.g. a GPG key ID or an SSH key path.
+    key: String,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SigningFormat {
+    Openpgp,
+    Ssh,
+}
+
+/// Settings for the commits created by lexicon edits.
+///
+/// The templates support the placeholders `{action}`, `{word}`, `{lexicon}`, `{user}`, `{user-id}`
+/// and `{timestamp}` (seconds since the Unix epoch).
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct CommitConfig {
+    message: String,
+    author: CommitAuthor,
+    /// Email used for the Discord user when it is credited in a commit.
+    email: String,
+}
+
+impl Default for CommitConfig {
+    fn default() -> Self {
+        Self {
+            message: "[lexicon] {action} {word} - {user}".to_string(),
+            author: CommitAuthor::default(),
+            email: "{user-id}@discord.invalid".to_string(),
+        }
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommitAuthor {
+    /// Commit as the configured git user.
+    #[default]
+    Bot,
+    /// Commit with the Discord user as author.
+    User,
+    /// Commit as the configured git user with a co-author trailer for the Discord user.
+    CoAuthor,
+}
+
+fn default_sync_interval() -> u64 {
+    300
+}
+
+fn default_git_timeout() -> u64 {
+    60
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct LexiconConfig {
     name: String,
+    /// Name of the repository, the default repository if absent.
+    #[serde(default)]
+    repository: Option<String>,
+    /// Directory of the lexicon files relative to the repository root.
+    #[serde(default)]
+    directory: PathBuf,
     file: PathBuf,
     target_file: PathBuf,
+    #[serde(default)]
+    access: AccessConfig,
+    /// Guilds the lexicon is restricted to, available everywhere if empty.
+    #[serde(default)]
+    guilds: Vec<GuildId>,
+    /// How the target file is rendered.
+    #[serde(default)]
+    render: RenderConfig,
+    /// Additional files rendered from the lexicon.
+    #[serde(default)]
+    outputs: Vec<OutputConfig>,
+    #[serde(default)]
+    collation: CollationConfig,
+    #[serde(default)]
+    validation: ValidationConfig,
+    #[serde(default)]
+    word_of_the_day: Option<WordOfTheDayConfig>,
+}
+
+/// A daily post of an entry that was not posted before.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WordOfTheDayConfig {
+    channel: ChannelId,
+    /// Time of the post in UTC as `HH:MM`.
+    #[serde(default = "default_word_of_the_day_time")]
+    time: String,
+}
+
+fn default_word_of_the_day_time() -> String {
+    "09:00".to_string()
+}
+
+/// Rules for the words added to a lexicon.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ValidationConfig {
+    /// Remove leading and trailing whitespace.
+    trim: bool,
+    /// Replace runs of whitespace with a single space.
+    collapse_whitespace: bool,
+    min_length: usize,
+    max_length: usize,
+    /// Character classes words may consist of, any character is allowed if this and
+    /// `allowed-characters` are empty.
+    allowed: Vec<CharacterClass>,
+    /// Characters allowed in addition to the classes.
+    allowed_characters: String,
+    /// Words that cannot be added, compared case-insensitively.
+    reserved: Vec<String>,
+    capitalization: Capitalization,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            trim: true,
+            collapse_whitespace: true,
+            min_length: 2,
+            max_length: 50,
+            allowed: Vec::new(),
+            allowed_characters: String::new(),
+            reserved: Vec::new(),
+            capitalization: Capitalization::Any,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CharacterClass {
+    Letter,
+    Digit,
+    Whitespace,
+    Punctuation,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capitalization {
+    #[default]
+    Any,
+    Lowercase,
+    Uppercase,
+    /// The first character must not be lowercase.
+    Capitalized,
+}
+
+/// How the entries of a lexicon are grouped by letter and sorted.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct CollationConfig {
+    /// List letters with diacritics under their base letter, like "Ä" under "A".
+    fold_diacritics: bool,
+    /// The letters in alphabetical order, which may include digraphs like "CH".
+    ///
+    /// Letters that are not listed are sorted like their base letter or after the listed ones.
+    alphabet: Vec<String>,
+}
+
+/// Templates for a generated file, absent templates use the defaults of the format.
+///
+/// Templates may use `{lexicon}`, `{count}` and `{toc}` in the header and footer, `{letter}` and
+/// `{anchor}` in the letter and table of contents entries and additionally `{word}` and
+/// `{description}` in the entries.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct RenderConfig {
+    format: OutputFormat,
+    header: Option<String>,
+    footer: Option<String>,
+    toc_entry: Option<String>,
+    letter: Option<String>,
+    entry: Option<String>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    #[default]
+    Markdown,
+    Html,
+    Asciidoc,
+    /// A JSON index of all entries, templates are ignored.
+    Json,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct OutputConfig {
+    /// Path of the file relative to the lexicon directory.
+    file: PathBuf,
+    #[serde(flatten)]
+    render: RenderConfig,
+}
+
+/// Who may read, suggest changes to, edit and administrate a lexicon.
+///
+/// An absent rule grants the level to everyone with the level below it, except for `admin`
+/// which nobody has by default.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct AccessConfig {
+    read: Option<AccessRule>,
+    suggest: Option<AccessRule>,
+    edit: Option<AccessRule>,
+    admin: Option<AccessRule>,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct AccessRule {
+    roles: Vec<RoleId>,
+    users: Vec<UserId>,
 }
 
 pub enum Response {
-    Regular {
-        success: bool,
-        title: String,
-        text: String,
-    },
+    Message(Message),
     Modal {
         creation: fn(&Handler, &mut CreateInteractionResponseData),
         modal: Modal,
     },
+    /// Work that may exceed the interaction deadline, the interaction is acknowledged first and
+    /// the response is edited once the work is done.
+    Deferred(Work),
 }
 
-impl Response {
-    pub fn success(title: impl Into<String>, text: impl Into<String>) -> Self {
-        Self::Regular {
-            success: true,
-            title: title.into(),
-            text: text.into(),
+pub type Work = Box<dyn FnOnce(&Handler) -> Response + Send>;
+
+const GREEN: Color = Color::from_rgb(0x4b, 0xb5, 0x43);
+const RED: Color = Color::from_rgb(0xcc, 0x00, 0x00);
+const BLURPLE: Color = Color::from_rgb(0x58, 0x65, 0xf2);
+
+// limits of Discord for the characters in parts of an embed and for a message
+const TITLE_LIMIT: usize = 256;
+const DESCRIPTION_LIMIT: usize = 4096;
+const FIELD_COUNT: usize = 25;
+const FIELD_NAME_LIMIT: usize = 256;
+const FIELD_VALUE_LIMIT: usize = 1024;
+const FOOTER_LIMIT: usize = 2048;
+const MESSAGE_LIMIT: usize = 6000;
+const EMBED_COUNT: usize = 10;
+/// Messages a response may be split into before it is sent as a file instead.
+const MESSAGE_COUNT: usize = 5;
+
+/// A message of one or more embeds with optional attachments and components.
+#[derive(Default)]
+pub struct Message {
+    embeds: Vec<Embed>,
+    attachments: Vec<Attachment>,
+    rows: Vec<Row>,
+    /// Only the user who caused the interaction sees the message.
+    ephemeral: bool,
+    /// Whether the message reports an error.
+    failed: bool,
+}
+
+struct Embed {
+    title: String,
+    text: String,
+    color: Color,
+    /// Name, value and whether the field is shown inline.
+    fields: Vec<(String, String, bool)>,
+    footer: Option<String>,
+}
+
+struct Attachment {
+    name: String,
+    data: Vec<u8>,
+}
+
+/// An action row, select menus take up a whole row.
+enum Row {
+    Buttons(Vec<Button>),
+    Select(Select),
+}
+
+pub struct Button {
+    id: String,
+    label: String,
+    style: ButtonStyle,
+}
+
+impl Button {
+    pub fn new(id: impl Into<String>, label: impl Into<String>, style: ButtonStyle) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+            style,
         }
     }
+}
 
-    pub fn failure(title: impl Into<String>, text: impl Into<String>) -> Self {
-        Self::Regular {
-            success: false,
+pub struct Select {
+    id: String,
+    placeholder: String,
+    /// Label and value of each option.
+    options: Vec<(String, String)>,
+}
+
+impl Select {
+    pub fn new(
+        id: impl Into<String>,
+        placeholder: impl Into<String>,
+        options: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            placeholder: placeholder.into(),
+            options,
+        }
+    }
+}
+
+impl Embed {
+    fn new(title: impl Into<String>, text: impl Into<String>, color: Color) -> Self {
+        Self {
             title: title.into(),
             text: text.into(),
+            color,
+            fields: Vec::new(),
+            footer: None,
         }
     }
 
-    pub fn invalid_command() -> Self {
-        Self::Regular {
-            success: false,
-            title: "Internal error".to_string(),
-            text: "The command is invalid.".to_string(),
+    /// Characters of the embed as counted against the message limit.
+    fn size(&self) -> usize {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value, _)| name.chars().count() + value.chars().count())
+            .sum::<usize>();
+        let footer = self
+            .footer
+            .as_ref()
+            .map(|footer| footer.chars().count())
+            .unwrap_or_default();
+        self.title.chars().count() + self.text.chars().count() + fields + footer
+    }
+
+    /// Splits a too long description over several embeds and shortens everything else.
+    ///
+    /// The fields follow the description and move to further embeds when an embed would exceed
+    /// the message limit, the footer is kept on the last embed.
+    fn split(self) -> Vec<Embed> {
+        let title = split::truncate(&self.title, TITLE_LIMIT);
+        let fields = self
+            .fields
+            .into_iter()
+            .map(|(name, value, inline)| {
+                (
+                    split::truncate(&name, FIELD_NAME_LIMIT),
+                    split::truncate(&value, FIELD_VALUE_LIMIT),
+                    inline,
+                )
+            })
+            .collect::<Vec<_>>();
+        let footer = self
+            .footer
+            .map(|footer| split::truncate(&footer, FOOTER_LIMIT));
+        let mut embeds = split::chunks(&self.text, DESCRIPTION_LIMIT)
+            .into_iter()
+            .map(|text| Embed::new(String::new(), text, self.color))
+            .collect::<Vec<_>>();
+        if embeds.is_empty() {
+            embeds.push(Embed::new(String::new(), String::new(), self.color));
+        }
+        // the titles are set once the number of embeds is known, so space is kept for them
+        let fits = |embed: &Embed, size: usize| TITLE_LIMIT + embed.size() + size <= MESSAGE_LIMIT;
+        for field in fields {
+            let last = embeds.last().unwrap();
+            let size = field.0.chars().count() + field.1.chars().count();
+            if last.fields.len() == FIELD_COUNT || !fits(last, size) {
+                embeds.push(Embed::new(String::new(), String::new(), self.color));
+            }
+            embeds.last_mut().unwrap().fields.push(field);
+        }
+        if let Some(footer) = footer {
+            if !fits(embeds.last().unwrap(), footer.chars().count()) {
+                embeds.push(Embed::new(String::new(), String::new(), self.color));
+            }
+            embeds.last_mut().unwrap().footer = Some(footer);
         }
+        let count = embeds.len();
+        for (index, embed) in embeds.iter_mut().enumerate() {
+            embed.title = if index == 0 {
+                title.clone()
+            } else {
+                let suffix = format!(" ({}/{count})", index + 1);
+                let title = split::truncate(&title, TITLE_LIMIT - suffix.len());
+                title + &suffix
+            };
+        }
+        embeds
     }
 
-    pub fn unimplemented() -> Self {
-        Self::Regular {
-            success: false,
-            title: "Internal error".to_string(),
-            text: "The command is not implemented.".to_string(),
+    /// The embed as plain text for a file.
+    fn plain(&self) -> String {
+        let mut plain = format!("{}\n\n{}\n", self.title, self.text);
+        for (name, value, _) in &self.fields {
+            plain.push_str(&format!("\n{name}\n{value}\n"));
+        }
+        if let Some(footer) = &self.footer {
+            plain.push_str(&format!("\n{footer}\n"));
+        }
+        plain
+    }
+
+    fn create(self) -> CreateEmbed {
+        let mut embed = CreateEmbed::default();
+        embed.title(self.title).color(self.color);
+        // Discord rejects empty descriptions, embeds with fields don't need one
+        if !self.text.is_empty() {
+            embed.description(self.text);
+        }
+        for (name, value, inline) in self.fields {
+            embed.field(name, value, inline);
+        }
+        if let Some(footer) = self.footer {
+            embed.footer(|create| create.text(footer));
+        }
+        embed
+    }
+}
+
+impl Message {
+    /// Splits the message into messages within Discord's limits.
+    ///
+    /// If that takes too many messages, the text is attached as a file instead. The components and
+    /// attachments are kept on the first message.
+    fn split(self) -> Vec<Message> {
+        let embeds = self
+            .embeds
+            .into_iter()
+            .flat_map(Embed::split)
+            .collect::<Vec<_>>();
+        let mut messages = vec![Message::default()];
+        let mut size = 0;
+        for embed in embeds {
+            let last = messages.last().unwrap();
+            let embed_size = embed.size();
+            if !last.embeds.is_empty()
+                && (last.embeds.len() == EMBED_COUNT || size + embed_size > MESSAGE_LIMIT)
+            {
+                messages.push(Message::default());
+                size = 0;
+            }
+            messages.last_mut().unwrap().embeds.push(embed);
+            size += embed_size;
+        }
+        if messages.len() > MESSAGE_COUNT {
+            let embeds = messages.iter().flat_map(|message| &message.embeds);
+            let plain = embeds.map(Embed::plain).collect::<Vec<_>>().join("\n");
+            let first = &messages[0].embeds[0];
+            let title = first.title.clone();
+            let color = first.color;
+            messages = vec![Message {
+                embeds: vec![Embed::new(
+                    title,
+                    "The response is too long to be shown, it is attached as a file.",
+                    color,
+                )],
+                attachments: vec![Attachment {
+                    name: "response.txt".to_string(),
+                    data: plain.into_bytes(),
+                }],
+                ..Default::default()
+            }];
         }
+        let first = &mut messages[0];
+        first.rows = self.rows;
+        first.attachments.extend(self.attachments);
+        for message in &mut messages {
+            message.ephemeral = self.ephemeral;
+        }
+        messages
+    }
+}
+
+impl Attachment {
+    fn create(self) -> AttachmentType<'static> {
+        AttachmentType::Bytes {
+            data: self.data.into(),
+            filename: self.name,
+        }
+    }
+}
+
+fn create_components(rows: Vec<Row>) -> CreateComponents {
+    let mut components = CreateComponents::default();
+    for row in rows {
+        components.create_action_row(|action_row| {
+            match row {
+                Row::Buttons(buttons) => {
+                    for button in buttons {
+                        action_row.create_button(|create| {
+                            create
+                                .custom_id(button.id)
+                                .label(button.label)
+                                .style(button.style)
+                        });
+                    }
+                }
+                Row::Select(select) => {
+                    action_row.create_select_menu(|menu| {
+                        menu.custom_id(select.id)
+                            .placeholder(select.placeholder)
+                            .options(|options| {
+                                for (label, value) in select.options {
+                                    options
+                                        .create_option(|option| option.label(label).value(value));
+                                }
+                                options
+                            })
+                    });
+                }
+            }
+            action_row
+        });
+    }
+    components
+}
+
+impl Response {
+    fn message(embed: Embed) -> Self {
+        Self::Message(Message {
+            embeds: vec![embed],
+            ..Default::default()
+        })
+    }
+
+    pub fn success(title: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::message(Embed::new(title, text, GREEN))
+    }
+
+    pub fn failure(title: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::message(Embed::new(title, text, RED)).map(|message| message.failed = true)
+    }
+
+    pub fn invalid_command() -> Self {
+        Self::failure("Internal error", "The command is invalid.")
+    }
+
+    pub fn unimplemented() -> Self {
+        Self::failure("Internal error", "The command is not implemented.")
     }
 
     pub fn modal(creation: fn(&Handler, &mut CreateInteractionResponseData), modal: Modal) -> Self {
         Self::Modal { creation, modal }
     }
 
-    pub fn handle(self, handler: &Handler, response: &mut CreateInteractionResponse<'_>) {
+    pub fn prompt(title: impl Into<String>, text: impl Into<String>, buttons: Vec<Button>) -> Self {
+        Self::Message(Message {
+            embeds: vec![Embed::new(title, text, BLURPLE)],
+            rows: vec![Row::Buttons(buttons)],
+            ..Default::default()
+        })
+    }
+
+    pub fn deferred(work: impl FnOnce(&Handler) -> Response + Send + 'static) -> Self {
+        Self::Deferred(Box::new(work))
+    }
+
+    /// Changes the message of the response, other responses are returned unchanged.
+    fn map(mut self, f: impl FnOnce(&mut Message)) -> Self {
+        if let Self::Message(message) = &mut self {
+            f(message);
+        }
+        self
+    }
+
+    /// Adds another embed.
+    pub fn embed(self, title: impl Into<String>, text: impl Into<String>) -> Self {
+        self.map(|message| message.embeds.push(Embed::new(title, text, BLURPLE)))
+    }
+
+    /// Adds a field to the last embed.
+    pub fn field(self, name: impl Into<String>, value: impl Into<String>, inline: bool) -> Self {
+        self.map(|message| {
+            if let Some(embed) = message.embeds.last_mut() {
+                embed.fields.push((name.into(), value.into(), inline));
+            }
+        })
+    }
+
+    /// Sets the footer of the last embed.
+    pub fn footer(self, text: impl Into<String>) -> Self {
+        self.map(|message| {
+            if let Some(embed) = message.embeds.last_mut() {
+                embed.footer = Some(text.into());
+            }
+        })
+    }
+
+    pub fn attachment(self, name: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        self.map(|message| {
+            message.attachments.push(Attachment {
+                name: name.into(),
+                data: data.into(),
+            })
+        })
+    }
+
+    pub fn select(self, select: Select) -> Self {
+        self.map(|message| message.rows.push(Row::Select(select)))
+    }
+
+    /// Shows the message only to the user who caused the interaction.
+    ///
+    /// Deferred responses are always visible to everyone.
+    pub fn ephemeral(self) -> Self {
+        self.map(|message| message.ephemeral = true)
+    }
+
+    /// Records the outcome on the span of the interaction and counts failures by the kind and
+    /// command of the interaction, as titles may contain arbitrary text.
+    fn record(&self, kind: &'static str, command: &str) {
+        let span = Span::current();
         match self {
-            Response::Regular {
-                success,
-                title,
-                text,
-            } => {
+            Response::Message(message) => {
+                let failure = message.embeds.first().filter(|_| message.failed);
+                span.record(
+                    "outcome",
+                    if message.failed { "failure" } else { "success" },
+                );
+                if let Some(embed) = failure {
+                    span.record("failure", embed.title.as_str());
+                    metrics::failure(kind, command);
+                }
+            }
+            Response::Modal { .. } => {
+                span.record("outcome", "modal");
+            }
+            Response::Deferred(_) => {
+                span.record("outcome", "deferred");
+            }
+        }
+    }
+
+    /// Fills in the interaction response and returns what remains to be sent afterwards.
+    pub fn handle(
+        self,
+        handler: &Handler,
+        response: &mut CreateInteractionResponse<'_>,
+    ) -> Remainder {
+        match self {
+            Response::Message(message) => {
+                let mut messages = message.split().into_iter();
+                let message = messages.next().unwrap_or_default();
                 response
                     .kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|message| {
-                        message.embed(|embed| {
-                            embed.title(title).description(text).color(if success {
-                                Color::from_rgb(0x4b, 0xb5, 0x43)
-                            } else {
-                                Color::from_rgb(0xcc, 0x00, 0x00)
-                            })
-                        })
+                    .interaction_response_data(|data| {
+                        data.add_embeds(message.embeds.into_iter().map(Embed::create).collect())
+                            .set_components(create_components(message.rows))
+                            .ephemeral(message.ephemeral);
+                        for attachment in message.attachments {
+                            data.add_file(attachment.create());
+                        }
+                        data
                     });
+                return Remainder::Followups(messages.collect());
             }
             Response::Modal { creation, modal } => {
-                let id = match modal {
-                    Modal::Test => "test".to_string(),
-                    Modal::LexiconAdd { .. } => {
-                        let mut modals = handler.modals.lock().unwrap();
-                        let id = modals.0;
-                        modals.1.insert(id, modal);
-                        modals.0 += 1;
-                        id.to_string()
-                    }
-                    Modal::LexiconUpdate { .. } => {
-                        let mut modals = handler.modals.lock().unwrap();
-                        let id = modals.0;
-                        modals.1.insert(id, modal);
-                        modals.0 += 1;
-                        id.to_string()
-                    }
-                    Modal::Emulate => "emulate".to_string(),
-                };
+                let id = handler.modals.lock().unwrap().insert(modal);
                 response
                     .kind(InteractionResponseType::Modal)
                     .interaction_response_data(|message| {
@@ -146,170 +893,597 @@ impl Response {
                         message.custom_id(id)
                     });
             }
+            Response::Deferred(work) => {
+                response.kind(InteractionResponseType::DeferredChannelMessageWithSource);
+                return Remainder::Deferred(work);
+            }
+        }
+        Remainder::Followups(Vec::new())
+    }
+
+    /// Replaces the "thinking" message of a deferred interaction with the response and returns
+    /// the follow-up messages.
+    pub fn edit(self, response: &mut EditInteractionResponse) -> Vec<Message> {
+        match self {
+            Response::Message(message) => {
+                let mut messages = message.split();
+                let message = messages.remove(0);
+                response
+                    .add_embeds(message.embeds.into_iter().map(Embed::create).collect())
+                    .components(|components| {
+                        *components = create_components(message.rows);
+                        components
+                    });
+                // attachments cannot be added by editing
+                if !message.attachments.is_empty() {
+                    messages.insert(
+                        0,
+                        Message {
+                            attachments: message.attachments,
+                            ..Default::default()
+                        },
+                    );
+                }
+                messages
+            }
+            // modals cannot be opened once the interaction is acknowledged
+            Response::Modal { .. } | Response::Deferred(_) => {
+                Response::invalid_command().edit(response)
+            }
         }
     }
 }
 
-pub enum Modal {
-    Test,
-    LexiconAdd { index: usize },
-    LexiconUpdate { index: usize },
-    Emulate,
+/// What is left to send once an interaction is responded to.
+pub enum Remainder {
+    Followups(Vec<Message>),
+    Deferred(Work),
+}
+
+/// An opened modal, its submission is routed to the command with the same name.
+#[derive(Deserialize, Serialize)]
+pub struct Modal {
+    pub command: String,
+    /// What the modal is for, interpreted by the command.
+    pub kind: String,
+    /// The state the command needs for the submission, like a lexicon name.
+    pub context: String,
+}
+
+impl Modal {
+    pub fn new(command: &str, kind: &str, context: impl Into<String>) -> Self {
+        Self {
+            command: command.to_string(),
+            kind: kind.to_string(),
+            context: context.into(),
+        }
+    }
 }
 
 pub struct Handler {
-    config: Config,
-    lexicons: Vec<Mutex<Lexicon>>,
-    modals: Mutex<(u64, HashMap<u64, Modal>)>,
+    commands: Vec<Box<dyn SlashCommand>>,
+    /// The configuration as it is persisted when lexicons are managed at runtime.
+    config: Mutex<Config>,
+    config_path: PathBuf,
+    /// The fields of the configuration that do not come from the config file.
+    overrides: Mutex<config::Overrides>,
+    repositories: Arc<Vec<Repository>>,
+    lexicons: Arc<RwLock<Vec<Slot>>>,
+    /// Guilds the bot is a member of.
+    guilds: Mutex<HashSet<GuildId>>,
+    suggestions: Mutex<Suggestions>,
+    modals: Arc<Mutex<Modals>>,
+    /// Whether the gateway connection is established.
+    connected: Arc<AtomicBool>,
+    quizzes: Mutex<Quizzes>,
+    quiz_scores: Mutex<Scores>,
+    changes: Mutex<ChangeLog>,
+    /// Shared with the task posting the word of the day.
+    word_of_the_day: Arc<Mutex<WordOfTheDayHistory>>,
     lexicon_add_modal: CreateComponents,
     lexicon_update_modal: CreateComponents,
 }
 
 impl Handler {
-    pub fn load(mut config: Config) -> Self {
-        if config.lexicons.is_empty() {
-            eprintln!("No lexicons present");
-            exit(1);
+    /// Sets up the repositories and loads the lexicons and persisted state.
+    ///
+    /// Every problem that keeps the bot from starting is reported, lexicons whose files cannot be
+    /// loaded are left out instead.
+    pub fn load(
+        config: Config,
+        config_path: PathBuf,
+        overrides: config::Overrides,
+    ) -> Result<Self, Vec<Error>> {
+        let mut errors = check::validate(&config);
+        let mut repositories = vec![Repository::new("default".to_string(), config.git.clone())];
+        for (name, repository_config) in &config.repositories {
+            // the name is reserved, which is reported by the validation
+            if name == "default" {
+                continue;
+            }
+            repositories.push(Repository::new(name.clone(), repository_config.clone()));
         }
-        if config.lexicons.len() > 25 {
-            eprintln!("Too many lexicons (max. 25)");
-            exit(1);
+        for repository in &repositories {
+            if let Err(err) = setup(repository) {
+                errors.push(err);
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
         }
         let mut lexicons = Vec::with_capacity(config.lexicons.len());
-        let lexicon_configs = mem::take(&mut config.lexicons);
-        for lexicon_config in lexicon_configs {
-            let lexicon = load(&config, lexicon_config);
-            lexicons.push(Mutex::new(lexicon));
+        for lexicon_config in &config.lexicons {
+            match load(&repositories, lexicon_config.clone()) {
+                Ok(slot) => lexicons.push(slot),
+                // a broken lexicon file must not take the other lexicons down
+                Err(err) => error!("Could not load lexicon: {err}"),
+            }
+        }
+        let mut report = |err| errors.push(err);
+        let suggestions = Suggestions::load(config.suggestions_file.clone()).map_err(&mut report);
+        let quiz_scores = Scores::load(config.quiz_scores_file.clone()).map_err(&mut report);
+        let changes = ChangeLog::load(config.change_log_file.clone()).map_err(&mut report);
+        let word_of_the_day =
+            WordOfTheDayHistory::load(config.word_of_the_day_file.clone()).map_err(&mut report);
+        let modals = Modals::load(&config.modals).map_err(&mut report);
+        let (Ok(suggestions), Ok(quiz_scores), Ok(changes), Ok(word_of_the_day), Ok(modals)) =
+            (suggestions, quiz_scores, changes, word_of_the_day, modals)
+        else {
+            return Err(errors);
+        };
+        if !errors.is_empty() {
+            return Err(errors);
         }
         let lexicon_add_modal = create_add_modal();
         let lexicon_update_modal = create_update_modal();
-        Self {
-            config,
-            modals: Mutex::new((0, HashMap::new())),
-            lexicons,
+        Ok(Self {
+            commands: commands::registry(),
+            repositories: Arc::new(repositories),
+            suggestions: Mutex::new(suggestions),
+            quiz_scores: Mutex::new(quiz_scores),
+            changes: Mutex::new(changes),
+            word_of_the_day: Arc::new(Mutex::new(word_of_the_day)),
+            modals: Arc::new(Mutex::new(modals)),
+            connected: Arc::new(AtomicBool::new(false)),
+            config: Mutex::new(config),
+            config_path,
+            overrides: Mutex::new(overrides),
+            guilds: Mutex::new(HashSet::new()),
+            quizzes: Mutex::new(Quizzes::default()),
+            lexicons: Arc::new(RwLock::new(lexicons)),
             lexicon_add_modal,
             lexicon_update_modal,
+        })
+    }
+
+    fn command(&self, name: &str) -> Option<&dyn SlashCommand> {
+        self.commands
+            .iter()
+            .find(|command| command.name() == name)
+            .map(Box::as_ref)
+    }
+
+    /// Responds to an interaction, running deferred work after acknowledging the interaction.
+    ///
+    /// The kind and command of the interaction are the ones it is counted by.
+    async fn respond(
+        &self,
+        http: &Http,
+        id: InteractionId,
+        token: &str,
+        kind: &'static str,
+        command: &str,
+        content: Response,
+    ) -> serenity::Result<()> {
+        content.record(kind, command);
+        let mut response = CreateInteractionResponse::default();
+        let remainder = content.handle(self, &mut response);
+        let files = response.1;
+        let response = Value::from(hashmap_to_json_map(response.0));
+        if files.is_empty() {
+            http.create_interaction_response(id.0, token, &response)
+                .await?;
+        } else {
+            http.create_interaction_response_with_files(id.0, token, &response, files)
+                .await?;
         }
+        let followups = match remainder {
+            Remainder::Followups(followups) => followups,
+            Remainder::Deferred(work) => {
+                // the work blocks on git, so it must not stall the other tasks of this worker
+                let content = tokio::task::block_in_place(|| work(self));
+                content.record(kind, command);
+                let mut response = EditInteractionResponse::default();
+                let followups = content.edit(&mut response);
+                let response = Value::from(hashmap_to_json_map(response.0));
+                http.edit_original_interaction_response(token, &response)
+                    .await?;
+                followups
+            }
+        };
+        for message in followups {
+            let mut followup = CreateInteractionResponseFollowup::default();
+            followup
+                .add_embeds(message.embeds.into_iter().map(Embed::create).collect())
+                .set_components(create_components(message.rows))
+                .ephemeral(message.ephemeral);
+            let files = message
+                .attachments
+                .into_iter()
+                .map(Attachment::create)
+                .collect::<Vec<_>>();
+            let followup = Value::from(hashmap_to_json_map(followup.0));
+            if files.is_empty() {
+                http.create_followup_message(token, &followup).await?;
+            } else {
+                http.create_followup_message_with_files(token, &followup, files)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the current configuration back to the config file.
+    pub fn save_config(&self) -> bool {
+        let config = self.config.lock().unwrap();
+        let Ok(config) = config::save(&config, &self.overrides.lock().unwrap()) else {
+            return false;
+        };
+        persist::write(&self.config_path, config).is_ok()
     }
 }
 
+/// A span for handling an interaction, the lexicon and the outcome are recorded once known.
+fn interaction_span(interaction: &Interaction) -> Span {
+    let (kind, name, user, guild_id) = match interaction {
+        Interaction::ApplicationCommand(command) => (
+            "command",
+            &command.data.name,
+            &command.user,
+            command.guild_id,
+        ),
+        Interaction::ModalSubmit(submission) => (
+            "modal",
+            &submission.data.custom_id,
+            &submission.user,
+            submission.guild_id,
+        ),
+        Interaction::MessageComponent(component) => (
+            "component",
+            &component.data.custom_id,
+            &component.user,
+            component.guild_id,
+        ),
+        Interaction::Autocomplete(autocomplete) => (
+            "autocomplete",
+            &autocomplete.data.name,
+            &autocomplete.user,
+            autocomplete.guild_id,
+        ),
+        _ => return Span::none(),
+    };
+    info_span!(
+        "interaction",
+        kind,
+        name = name.as_str(),
+        user = %user.tag(),
+        user_id = user.id.0,
+        guild_id = guild_id.map(|id| id.0),
+        lexicon = field::Empty,
+        outcome = field::Empty,
+        failure = field::Empty,
+    )
+}
+
+/// The lexicon a slash command refers to, if any.
+fn lexicon_option(options: &[CommandDataOption]) -> Option<&str> {
+    options
+        .iter()
+        .find_map(|option| match (option.name.as_str(), &option.value) {
+            ("lexicon", Some(Value::String(lexicon))) => Some(lexicon.as_str()),
+            _ => lexicon_option(&option.options),
+        })
+}
+
 #[async_trait]
 impl EventHandler for Handler {
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        match interaction {
-            Interaction::ApplicationCommand(command) => {
-                if command.guild_id.is_none() {
-                    println!("{} (DM) /{} - blocked", command.user, command.data.name);
-                    return;
+        let span = interaction_span(&interaction);
+        let start = Instant::now();
+        let latency_ms = || start.elapsed().as_millis() as u64;
+        async {
+            match interaction {
+                Interaction::ApplicationCommand(command) => {
+                    if command.guild_id.is_none() {
+                        info!("Blocked interaction in direct message");
+                        return;
+                    }
+                    metrics::interaction("command", &command.data.name);
+                    if let Some(lexicon) = lexicon_option(&command.data.options) {
+                        Span::current().record("lexicon", lexicon);
+                    }
+                    let content = match self.command(&command.data.name) {
+                        Some(slash_command) => slash_command.run(self, &ctx, &command).await,
+                        None => Response::unimplemented(),
+                    };
+                    if let Err(why) = self
+                        .respond(
+                            &ctx.http,
+                            command.id,
+                            &command.token,
+                            "command",
+                            &command.data.name,
+                            content,
+                        )
+                        .await
+                    {
+                        error!("Cannot respond to slash command: {why}");
+                    }
+                    info!(latency_ms = latency_ms(), "Interaction handled");
                 }
-                println!("{} /{}", command.user, command.data.name);
-                let content = match command.data.name.as_str() {
-                    "lexicon" => commands::lexicon::run(self, &command.user, &command.data.options),
-                    "emulate" => commands::emulate::run(&command.data.options),
-                    "test" => commands::test::run(&command.data.options),
-                    _ => Response::unimplemented(),
-                };
-                if let Err(why) = command
-                    .create_interaction_response(&ctx.http, move |response| {
-                        content.handle(self, response);
-                        response
-                    })
-                    .await
-                {
-                    eprintln!("Cannot respond to slash command: {}", why);
+                Interaction::ModalSubmit(mut submission) => {
+                    if submission.guild_id.is_none() {
+                        info!("Blocked interaction in direct message");
+                        return;
+                    }
+                    let modal = self.modals.lock().unwrap().take(&submission.data.custom_id);
+                    // the custom ID is random, so the modal is counted by its command
+                    let name = modal
+                        .as_ref()
+                        .map_or_else(|| "expired".to_string(), |modal| modal.command.clone());
+                    metrics::interaction("modal", &name);
+                    if let Some(modal) = modal.as_ref().filter(|modal| modal.command == "lexicon") {
+                        Span::current().record("lexicon", modal.context.as_str());
+                    }
+                    let command = modal
+                        .as_ref()
+                        .and_then(|modal| self.command(&modal.command));
+                    let content = match (modal, command) {
+                        (Some(modal), Some(command)) => {
+                            command.modal(self, modal, &mut submission).await
+                        }
+                        _ => Response::failure(
+                            "Modal expired",
+                            "The modal expired, please run the command again.",
+                        ),
+                    };
+                    if let Err(why) = self
+                        .respond(
+                            &ctx.http,
+                            submission.id,
+                            &submission.token,
+                            "modal",
+                            &name,
+                            content,
+                        )
+                        .await
+                    {
+                        error!("Cannot respond to modal: {why}");
+                    }
+                    info!(latency_ms = latency_ms(), "Interaction handled");
                 }
-            }
-            Interaction::ModalSubmit(mut submission) => {
-                if submission.guild_id.is_none() {
-                    println!(
-                        "{} (DM) modal:{} - blocked",
-                        submission.user, submission.data.custom_id
-                    );
-                    return;
+                Interaction::MessageComponent(component) => {
+                    if component.guild_id.is_none() {
+                        info!("Blocked interaction in direct message");
+                        return;
+                    }
+                    let name = component
+                        .data
+                        .custom_id
+                        .split(':')
+                        .next()
+                        .unwrap_or_default();
+                    metrics::interaction("component", name);
+                    let Some(command) = self.command(name) else {
+                        return;
+                    };
+                    let content = command.component(self, &component).await;
+                    if let Err(why) = self
+                        .respond(
+                            &ctx.http,
+                            component.id,
+                            &component.token,
+                            "component",
+                            name,
+                            content,
+                        )
+                        .await
+                    {
+                        error!("Cannot respond to message component: {why}");
+                    }
+                    info!(latency_ms = latency_ms(), "Interaction handled");
                 }
-                let custom_id = submission.data.custom_id.as_str();
-                let content = match custom_id {
-                    "test" => commands::test::handle_modal(&mut submission).await,
-                    "emulate" => commands::emulate::handle_modal(&mut submission).await,
-                    _ => {
-                        let Ok(custom_id) = submission.data.custom_id.parse() else {
-                            eprintln!("Cannot parse modal submission");
-                            return;
-                        };
-                        let modal = {
-                            let mut modals = self.modals.lock().unwrap();
-                            modals.1.remove(&custom_id)
-                        };
-                        let Some(modal) = modal else {
-                            return;
-                        };
-                        match modal {
-                            Modal::Test => commands::test::handle_modal(&mut submission).await,
-                            Modal::LexiconAdd { index } => {
-                                commands::lexicon::handle_add(self, index, &mut submission).await
-                            }
-                            Modal::LexiconUpdate { index } => {
-                                commands::lexicon::handle_update(self, index, &mut submission).await
-                            }
-                            Modal::Emulate => {
-                                commands::emulate::handle_modal(&mut submission).await
+                Interaction::Autocomplete(autocomplete) => {
+                    if autocomplete.guild_id.is_none() {
+                        return;
+                    }
+                    metrics::interaction("autocomplete", &autocomplete.data.name);
+                    let Some(command) = self.command(&autocomplete.data.name) else {
+                        return;
+                    };
+                    let choices = command.autocomplete(self, &autocomplete).await;
+                    if let Err(why) = autocomplete
+                        .create_autocomplete_response(&ctx.http, |response| {
+                            for choice in choices {
+                                response.add_string_choice(&choice, &choice);
                             }
-                        }
+                            response
+                        })
+                        .await
+                    {
+                        error!("Cannot respond to autocomplete: {why}");
                     }
-                };
-                if let Err(why) = submission
-                    .create_interaction_response(&ctx.http, |response| {
-                        content.handle(self, response);
-                        response
-                    })
-                    .await
-                {
-                    eprintln!("Cannot respond to slash command: {}", why);
+                    debug!(latency_ms = latency_ms(), "Interaction handled");
                 }
+                _ => {}
+            }
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn guild_create(&self, ctx: Context, guild: Guild) {
+        self.guilds.lock().unwrap().insert(guild.id);
+        for command in &self.commands {
+            if let Err(err) = command.register_guild(self, &ctx.http, guild.id).await {
+                error!(
+                    command = command.name(),
+                    guild_id = guild.id.0,
+                    "Could not create command in guild: {err}"
+                );
             }
-            _ => {}
         }
     }
 
     async fn ready(&self, ctx: Context, ready: Ready) {
-        println!("{} is connected!", ready.user.name);
-        Command::create_global_application_command(&ctx.http, |command| {
-            commands::lexicon::register(self, command)
-        })
-        .await
-        .expect("Create lexicon command");
-        Command::create_global_application_command(&ctx.http, |command| {
-            commands::emulate::register(command)
-        })
-        .await
-        .expect("Create emulate command");
-        Command::create_global_application_command(&ctx.http, |command| {
-            commands::test::register(command)
-        })
-        .await
-        .expect("Create test command");
+        info!(user = %ready.user.tag(), "Connected");
+        self.connected.store(true, Ordering::Relaxed);
+        for command in &self.commands {
+            if let Err(err) = command.register_global(self, &ctx.http).await {
+                error!(command = command.name(), "Could not create command: {err}");
+            }
+        }
+    }
+
+    async fn shard_stage_update(&self, _ctx: Context, event: ShardStageUpdateEvent) {
+        let connected = event.new == ConnectionStage::Connected;
+        if !connected && event.old == ConnectionStage::Connected {
+            info!(stage = %event.new, "Disconnected");
+        }
+        self.connected.store(connected, Ordering::Relaxed);
     }
 }
 
 #[tokio::main]
-async fn main() {
-    let config_src = fs::read_to_string(".config.toml");
-    if let Err(err) = config_src {
-        eprintln!("Could not read config file: {err}");
-        exit(1);
-    }
-    let config = toml::from_str(&config_src.unwrap());
-    if let Err(err) = config {
-        eprintln!("Could not parse config file: {err}");
-        exit(1);
-    }
-    let config: Config = config.unwrap();
-    setup(&config.git);
-    let mut client = Client::builder(&config.discord.token, GatewayIntents::empty())
-        .event_handler(Handler::load(config))
+async fn main() -> ExitCode {
+    let args = match cli::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n\n{}", cli::USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.help {
+        println!("{}", cli::USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let (config, overrides) = match config::load(&args.config, env::vars()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Could not load config: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.check_config {
+        let errors = check::check(&config);
+        for err in &errors {
+            eprintln!("{err}");
+        }
+        if !errors.is_empty() {
+            return ExitCode::FAILURE;
+        }
+        println!("The config is valid");
+        return ExitCode::SUCCESS;
+    }
+    if let Err(err) = logging::init(&config.log, args.log_level.as_deref()) {
+        eprintln!("{err}");
+        return ExitCode::FAILURE;
+    }
+    match run(args, config, overrides).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(errors) => {
+            for err in errors {
+                error!("{err}");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(
+    args: cli::Args,
+    config: Config,
+    overrides: config::Overrides,
+) -> Result<(), Vec<Error>> {
+    if args.dry_run && args.command.is_none() {
+        // setting up the repositories may clone or push, so only the checks run
+        let errors = check::check(&config);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        info!(
+            repositories = config.repositories.len() + 1,
+            lexicons = config.lexicons.len(),
+            "Dry run finished"
+        );
+        return Ok(());
+    }
+    let token = config.discord.token.clone();
+    let metrics_address = config.metrics.as_ref().map(|metrics| metrics.address);
+    let handler = Handler::load(config, args.config, overrides)?;
+    match args.command {
+        Some(cli::OneShot::Sync) => return sync(&handler),
+        Some(cli::OneShot::Export { lexicon }) => {
+            let lexicons = handler.lexicons.read().unwrap();
+            let rendered = commands::lexicon::export(&lexicons, &lexicon).map_err(|err| {
+                vec![Error::Lexicon {
+                    name: lexicon,
+                    reason: err,
+                }]
+            })?;
+            print!("{rendered}");
+            return Ok(());
+        }
+        None => {}
+    }
+    for repository in 0..handler.repositories.len() {
+        tokio::spawn(commands::lexicon::watch(
+            handler.repositories.clone(),
+            repository,
+            handler.lexicons.clone(),
+        ));
+    }
+    if let Some(address) = metrics_address {
+        tokio::spawn(metrics::serve(
+            address,
+            metrics::State {
+                connected: handler.connected.clone(),
+                repositories: handler.repositories.clone(),
+                lexicons: handler.lexicons.clone(),
+                modals: handler.modals.clone(),
+            },
+        ));
+    }
+    let lexicons = handler.lexicons.clone();
+    let history = handler.word_of_the_day.clone();
+    let mut client = Client::builder(&token, GatewayIntents::GUILDS)
+        .event_handler(handler)
         .await
-        .expect("Error creating client");
-    if let Err(err) = client.start().await {
-        eprintln!("Client error: {err}");
+        .map_err(|err| vec![Error::from(err)])?;
+    tokio::spawn(commands::lexicon::word_of_the_day::run(
+        client.cache_and_http.http.clone(),
+        lexicons,
+        history,
+    ));
+    client.start().await.map_err(|err| vec![Error::from(err)])
+}
+
+/// Syncs every repository once, failing if any of them could not be synced.
+fn sync(handler: &Handler) -> Result<(), Vec<Error>> {
+    let lexicons = handler.lexicons.read().unwrap();
+    let mut errors = Vec::new();
+    for (index, repository) in handler.repositories.iter().enumerate() {
+        commands::lexicon::sync(&handler.repositories, index, &lexicons);
+        if !repository.synced() {
+            errors.push(Error::Git {
+                repository: repository.name.clone(),
+                reason: "Could not sync repository".to_string(),
+            });
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
     }
 }
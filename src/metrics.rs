@@ -0,0 +1,283 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, error, info};
+
+use crate::{
+    commands::lexicon::{self, Slot},
+    git::{self, Repository, Status},
+    modals::Modals,
+};
+
+/// Counters recorded anywhere in the bot, the gauges are read when the metrics are requested.
+static COUNTERS: Mutex<Counters> = Mutex::new(Counters {
+    interactions: BTreeMap::new(),
+    failures: BTreeMap::new(),
+    git: BTreeMap::new(),
+});
+
+struct Counters {
+    /// Interactions by kind and command.
+    interactions: BTreeMap<(&'static str, String), u64>,
+    /// Failed responses by the kind and command of the interaction.
+    failures: BTreeMap<(&'static str, String), u64>,
+    /// Git commands by subcommand.
+    git: BTreeMap<String, GitCounters>,
+}
+
+#[derive(Default)]
+struct GitCounters {
+    succeeded: u64,
+    failed: u64,
+    timed_out: u64,
+    seconds: f64,
+}
+
+pub fn interaction(kind: &'static str, command: &str) {
+    let mut counters = COUNTERS.lock().unwrap();
+    *counters
+        .interactions
+        .entry((kind, command.to_string()))
+        .or_default() += 1;
+}
+
+pub fn failure(kind: &'static str, command: &str) {
+    let mut counters = COUNTERS.lock().unwrap();
+    *counters
+        .failures
+        .entry((kind, command.to_string()))
+        .or_default() += 1;
+}
+
+pub fn git(command: &str, duration: Duration, status: Status) {
+    let mut counters = COUNTERS.lock().unwrap();
+    let git = counters.git.entry(command.to_string()).or_default();
+    match status {
+        Status::Success => git.succeeded += 1,
+        Status::Failure => git.failed += 1,
+        Status::TimedOut => git.timed_out += 1,
+    }
+    git.seconds += duration.as_secs_f64();
+}
+
+/// What the endpoint reports besides the counters.
+#[derive(Clone)]
+pub struct State {
+    /// Whether the gateway connection is established.
+    pub connected: Arc<AtomicBool>,
+    pub repositories: Arc<Vec<Repository>>,
+    pub lexicons: Arc<RwLock<Vec<Slot>>>,
+    pub modals: Arc<Mutex<Modals>>,
+}
+
+/// Serves `/metrics`, `/healthz` and `/readyz` until the bot stops.
+pub async fn serve(address: SocketAddr, state: State) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(%address, "Could not start metrics endpoint: {err}");
+            return;
+        }
+    };
+    info!(%address, "Serving metrics");
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                debug!("Could not accept metrics connection: {err}");
+                continue;
+            }
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(err) = respond(stream, state).await {
+                debug!("Could not answer metrics request: {err}");
+            }
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, state: State) -> io::Result<()> {
+    let mut buffer = [0; 1024];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let path = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(path)) => path,
+        _ => "",
+    };
+    let (status, body) = match path {
+        // gauges lock the lexicons, which may be held during git operations
+        "/metrics" => match tokio::task::spawn_blocking(move || render(&state)).await {
+            Ok(metrics) => ("200 OK", metrics),
+            Err(_) => ("500 Internal Server Error", "error\n".to_string()),
+        },
+        "/healthz" => ("200 OK", "ok\n".to_string()),
+        "/readyz" => match ready(&state) {
+            Ok(()) => ("200 OK", "ready\n".to_string()),
+            Err(reason) => ("503 Service Unavailable", format!("{reason}\n")),
+        },
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// The bot is ready once it is connected to the gateway and every repository is in sync.
+fn ready(state: &State) -> Result<(), String> {
+    if !state.connected.load(Ordering::Relaxed) {
+        return Err("not connected to the gateway".to_string());
+    }
+    let unsynced = state
+        .repositories
+        .iter()
+        .filter(|repository| !repository.synced())
+        .map(|repository| repository.name.as_str())
+        .collect::<Vec<_>>();
+    if !unsynced.is_empty() {
+        return Err(format!("repositories out of sync: {}", unsynced.join(", ")));
+    }
+    Ok(())
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(metrics: &mut String, name: &str, kind: &str, help: &str) {
+    metrics.push_str(&format!("# HELP {name} {help}\n"));
+    metrics.push_str(&format!("# TYPE {name} {kind}\n"));
+}
+
+fn render(state: &State) -> String {
+    let mut metrics = String::new();
+    {
+        let counters = COUNTERS.lock().unwrap();
+        let name = "sqyyy_bot_interactions_total";
+        header(
+            &mut metrics,
+            name,
+            "counter",
+            "Interactions by kind and command.",
+        );
+        for ((kind, command), count) in &counters.interactions {
+            let command = escape(command);
+            metrics.push_str(&format!(
+                "{name}{{kind=\"{kind}\",command=\"{command}\"}} {count}\n"
+            ));
+        }
+        let name = "sqyyy_bot_failures_total";
+        header(
+            &mut metrics,
+            name,
+            "counter",
+            "Failed responses by kind and command.",
+        );
+        for ((kind, command), count) in &counters.failures {
+            let command = escape(command);
+            metrics.push_str(&format!(
+                "{name}{{kind=\"{kind}\",command=\"{command}\"}} {count}\n"
+            ));
+        }
+        let name = "sqyyy_bot_git_operations_total";
+        header(
+            &mut metrics,
+            name,
+            "counter",
+            "Git commands by subcommand and status.",
+        );
+        for (command, git) in &counters.git {
+            let statuses = [
+                ("success", git.succeeded),
+                ("failure", git.failed),
+                ("timeout", git.timed_out),
+            ];
+            for (status, count) in statuses {
+                metrics.push_str(&format!(
+                    "{name}{{command=\"{}\",status=\"{status}\"}} {count}\n",
+                    escape(command)
+                ));
+            }
+        }
+        let name = "sqyyy_bot_git_duration_seconds";
+        header(&mut metrics, name, "summary", "Time spent in git commands.");
+        for (command, git) in &counters.git {
+            let command = escape(command);
+            let count = git.succeeded + git.failed + git.timed_out;
+            metrics.push_str(&format!(
+                "{name}_sum{{command=\"{command}\"}} {}\n",
+                git.seconds
+            ));
+            metrics.push_str(&format!("{name}_count{{command=\"{command}\"}} {count}\n"));
+        }
+    }
+    let name = "sqyyy_bot_lexicon_entries";
+    header(&mut metrics, name, "gauge", "Entries per lexicon.");
+    for (lexicon, entries) in lexicon::sizes(&state.lexicons.read().unwrap()) {
+        metrics.push_str(&format!(
+            "{name}{{lexicon=\"{}\"}} {entries}\n",
+            escape(&lexicon)
+        ));
+    }
+    let name = "sqyyy_bot_pending_modals";
+    header(
+        &mut metrics,
+        name,
+        "gauge",
+        "Opened modals that were not submitted yet.",
+    );
+    let pending = state.modals.lock().unwrap().pending();
+    metrics.push_str(&format!("{name} {pending}\n"));
+    let name = "sqyyy_bot_gateway_connected";
+    header(
+        &mut metrics,
+        name,
+        "gauge",
+        "Whether the gateway connection is established.",
+    );
+    let connected = u8::from(state.connected.load(Ordering::Relaxed));
+    metrics.push_str(&format!("{name} {connected}\n"));
+    let name = "sqyyy_bot_repository_synced";
+    header(
+        &mut metrics,
+        name,
+        "gauge",
+        "Whether the last sync of a repository succeeded.",
+    );
+    for repository in state.repositories.iter() {
+        let synced = u8::from(repository.synced());
+        metrics.push_str(&format!(
+            "{name}{{repository=\"{}\"}} {synced}\n",
+            escape(&repository.name)
+        ));
+    }
+    let name = "sqyyy_bot_repository_pending_commits";
+    header(&mut metrics, name, "gauge", "Commits waiting to be pushed.");
+    for repository in state.repositories.iter() {
+        let pending = git::outbox(&repository.config).len();
+        metrics.push_str(&format!(
+            "{name}{{repository=\"{}\"}} {pending}\n",
+            escape(&repository.name)
+        ));
+    }
+    metrics
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::{error::Error, Modal, ModalConfig};
+
+/// Modals that were opened but not submitted yet.
+///
+/// Custom ids contain a random nonce so ids from before a restart never match a newer modal.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Modals {
+    pending: HashMap<String, Pending>,
+    #[serde(skip)]
+    ttl: u64,
+    /// File the pending modals are persisted in, if enabled.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Pending {
+    modal: Modal,
+    /// Seconds since the Unix epoch after which the modal is discarded.
+    expires: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+impl Modals {
+    pub fn load(config: &ModalConfig) -> Result<Self, Error> {
+        let empty = Self {
+            ttl: config.ttl,
+            path: config.file.clone(),
+            ..Default::default()
+        };
+        let Some(path) = &config.file else {
+            return Ok(empty);
+        };
+        if !path.exists() {
+            return Ok(empty);
+        }
+        let modals = fs::read_to_string(path).map_err(|err| Error::file(path, err))?;
+        // pending modals are disposable, so unreadable ones are dropped
+        let Ok(mut modals) = ron::from_str::<Self>(&modals) else {
+            warn!("Could not parse pending modals, discarding them");
+            return Ok(empty);
+        };
+        modals.ttl = empty.ttl;
+        modals.path = empty.path;
+        modals.evict();
+        Ok(modals)
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let saved = ron::to_string(self)
+            .ok()
+            .map(|modals| fs::write(path, modals).is_ok())
+            .unwrap_or(false);
+        if !saved {
+            error!("Could not save pending modals");
+        }
+    }
+
+    fn evict(&mut self) {
+        let now = now();
+        self.pending.retain(|_, pending| pending.expires > now);
+    }
+
+    /// Remembers an opened modal and returns its custom id.
+    pub fn insert(&mut self, modal: Modal) -> String {
+        self.evict();
+        let id = format!(
+            "{:016x}:{}:{}:{}",
+            rand::random::<u64>(),
+            modal.command,
+            modal.kind,
+            modal.context
+        );
+        // custom ids are limited to 100 characters, the nonce keeps them unique
+        let id = id.chars().take(100).collect::<String>();
+        self.pending.insert(
+            id.clone(),
+            Pending {
+                modal,
+                expires: now() + self.ttl,
+            },
+        );
+        self.save();
+        id
+    }
+
+    /// Counts the modals that are still valid.
+    pub fn pending(&mut self) -> usize {
+        self.evict();
+        self.pending.len()
+    }
+
+    /// Removes a submitted modal, `None` if it is unknown or expired.
+    pub fn take(&mut self, id: &str) -> Option<Modal> {
+        self.evict();
+        let pending = self.pending.remove(id)?;
+        self.save();
+        Some(pending.modal)
+    }
+}
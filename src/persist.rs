@@ -0,0 +1,31 @@
+use std::{fs, io, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::Error;
+
+/// Writes a file through a temporary file that is renamed into place, so a crash while writing
+/// never leaves the file cut off.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
+
+/// Reads state persisted as RON, the default if the file does not exist yet.
+pub fn load<T: Default + DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let state = fs::read_to_string(path).map_err(|err| Error::file(path, err))?;
+    ron::from_str(&state).map_err(|err| Error::file(path, err))
+}
+
+/// Persists state as RON, returning whether it was written.
+pub fn save<T: Serialize>(path: &Path, state: &T) -> bool {
+    let Ok(state) = ron::to_string(state) else {
+        return false;
+    };
+    write(path, state).is_ok()
+}
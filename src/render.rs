@@ -0,0 +1,151 @@
+use serde::Serialize;
+
+use crate::{collation::Group, template, OutputFormat, RenderConfig};
+
+const BANNER: &str = "THIS FILE IS AUTOMATICALLY GENERATED - DO NOT EDIT";
+
+struct Templates {
+    header: String,
+    footer: &'static str,
+    toc_entry: &'static str,
+    letter: &'static str,
+    entry: &'static str,
+}
+
+#[derive(Serialize)]
+struct Index<'a> {
+    lexicon: &'a str,
+    entries: Vec<IndexEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct IndexEntry<'a> {
+    word: &'a str,
+    description: &'a str,
+    letter: &'a str,
+    anchor: String,
+}
+
+fn defaults(format: OutputFormat) -> Templates {
+    match format {
+        // JSON indexes are serialized directly and never use templates
+        OutputFormat::Markdown | OutputFormat::Json => Templates {
+            header: format!("<!--{BANNER}-->\n"),
+            footer: "",
+            toc_entry: "- [{letter}](#{anchor})\n",
+            letter: "\n## {letter}\n",
+            entry: "\n### {word}\n\n{description}\n",
+        },
+        OutputFormat::Html => Templates {
+            header: format!("<!--{BANNER}-->\n"),
+            footer: "",
+            toc_entry: "<li><a href=\"#{anchor}\">{letter}</a></li>\n",
+            letter: "<h2 id=\"{anchor}\">{letter}</h2>\n",
+            entry: "<h3 id=\"{anchor}\">{word}</h3>\n<p>{description}</p>\n",
+        },
+        OutputFormat::Asciidoc => Templates {
+            header: format!("// {BANNER}\n"),
+            footer: "",
+            toc_entry: "* <<{anchor},{letter}>>\n",
+            letter: "\n[[{anchor}]]\n== {letter}\n",
+            entry: "\n[[{anchor}]]\n=== {word}\n\n{description}\n",
+        },
+    }
+}
+
+/// Builds an anchor the way GitHub does for Markdown headings.
+fn anchor(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Renders the entries of a lexicon into the contents of a generated file.
+pub fn render(lexicon: &str, groups: &[Group], config: &RenderConfig) -> Result<String, String> {
+    if let OutputFormat::Json = config.format {
+        return render_json(lexicon, groups);
+    }
+    let escape = |text: &str| match config.format {
+        OutputFormat::Html => escape_html(text),
+        _ => text.to_string(),
+    };
+    let defaults = defaults(config.format);
+    let header = config.header.as_deref().unwrap_or(&defaults.header);
+    let footer = config.footer.as_deref().unwrap_or(defaults.footer);
+    let toc_entry = config.toc_entry.as_deref().unwrap_or(defaults.toc_entry);
+    let letter_template = config.letter.as_deref().unwrap_or(defaults.letter);
+    let entry_template = config.entry.as_deref().unwrap_or(defaults.entry);
+    let mut toc = String::new();
+    let mut body = String::new();
+    for group in groups {
+        let letter = escape(&group.letter);
+        let letter_anchor = anchor(&letter);
+        let values = [("letter", letter.as_str()), ("anchor", &letter_anchor)];
+        toc.push_str(&template::render(toc_entry, &values));
+        body.push_str(&template::render(letter_template, &values));
+        for (word, description) in &group.entries {
+            let word_anchor = anchor(word);
+            let word = escape(word);
+            let description = escape(description);
+            body.push_str(&template::render(
+                entry_template,
+                &[
+                    ("letter", &letter),
+                    ("anchor", &word_anchor),
+                    ("word", &word),
+                    ("description", &description),
+                ],
+            ));
+        }
+    }
+    let lexicon = escape(lexicon);
+    let count = groups
+        .iter()
+        .map(|group| group.entries.len())
+        .sum::<usize>()
+        .to_string();
+    let values = [
+        ("lexicon", lexicon.as_str()),
+        ("count", &count),
+        ("toc", &toc),
+    ];
+    let mut result = template::render(header, &values);
+    result.push_str(&body);
+    result.push_str(&template::render(footer, &values));
+    Ok(result)
+}
+
+fn render_json(lexicon: &str, groups: &[Group]) -> Result<String, String> {
+    let entries = groups
+        .iter()
+        .flat_map(|group| {
+            group.entries.iter().map(|(word, description)| IndexEntry {
+                word,
+                description,
+                letter: &group.letter,
+                anchor: anchor(word),
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&Index { lexicon, entries }).map_err(|err| err.to_string())
+}
@@ -0,0 +1,111 @@
+const FENCE: &str = "```";
+
+/// Splits text into chunks of at most `limit` characters, breaking at line ends where possible.
+///
+/// Code blocks that span several chunks are closed at the end of a chunk and reopened in the next.
+pub fn chunks(text: &str, limit: usize) -> Vec<String> {
+    // room to close a code block at the end of a chunk and reopen it in the next one
+    let reserved = FENCE.len() + 1;
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut length = 0;
+    let mut fenced = false;
+    for line in text.split_inclusive('\n') {
+        for piece in pieces(line, limit - 2 * reserved) {
+            let piece_length = piece.chars().count();
+            let toggles = piece.matches(FENCE).count() % 2 == 1;
+            let needed = if fenced || toggles { reserved } else { 0 };
+            if length > 0 && length + piece_length + needed > limit {
+                if fenced {
+                    if !chunk.ends_with('\n') {
+                        chunk.push('\n');
+                    }
+                    chunk.push_str(FENCE);
+                }
+                chunks.push(std::mem::take(&mut chunk));
+                length = 0;
+                if fenced {
+                    chunk.push_str(FENCE);
+                    chunk.push('\n');
+                    length = reserved;
+                }
+            }
+            chunk.push_str(piece);
+            length += piece_length;
+            if toggles {
+                fenced = !fenced;
+            }
+        }
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+/// Breaks a line into pieces of at most `limit` characters.
+fn pieces(line: &str, limit: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = line;
+    while let Some((index, _)) = rest.char_indices().nth(limit) {
+        let (piece, remainder) = rest.split_at(index);
+        pieces.push(piece);
+        rest = remainder;
+    }
+    pieces.push(rest);
+    pieces
+}
+
+/// Shortens text to at most `limit` characters, marking the cut with an ellipsis.
+pub fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+    let mut truncated = text.chars().take(limit - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_one_chunk() {
+        assert_eq!(chunks("first\nsecond", 100), ["first\nsecond"]);
+    }
+
+    #[test]
+    fn breaks_at_line_ends() {
+        let line = "0123456789\n";
+        assert_eq!(chunks(&line.repeat(3), 20), [line, line, line]);
+    }
+
+    #[test]
+    fn breaks_long_lines() {
+        let text = "x".repeat(50);
+        let chunks = chunks(&text, 20);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 20));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn reopens_code_blocks() {
+        let text = format!("```\n{}```", "0123456789\n".repeat(4));
+        let chunks = chunks(&text, 30);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 30);
+            assert!(chunk.starts_with(FENCE));
+            assert!(chunk.ends_with(FENCE));
+            assert_eq!(chunk.matches(FENCE).count() % 2, 0);
+        }
+    }
+
+    #[test]
+    fn counts_characters_instead_of_bytes() {
+        assert_eq!(chunks("äöü\n", 10), ["äöü\n"]);
+        assert_eq!(truncate("äöüß", 4), "äöüß");
+        assert_eq!(truncate("äöüß", 3), "äö…");
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serenity::model::{id::UserId, user::User};
+
+use crate::{error::Error, persist};
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub enum SuggestionKind {
+    Add,
+    Update,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Suggestion {
+    pub id: u64,
+    pub lexicon: String,
+    pub kind: SuggestionKind,
+    pub word: String,
+    pub description: String,
+    pub user_name: String,
+    pub user_id: UserId,
+}
+
+/// The persisted queue of lexicon changes waiting for review.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Suggestions {
+    next_id: u64,
+    pending: Vec<Suggestion>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Suggestions {
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let mut suggestions = persist::load::<Self>(&path)?;
+        suggestions.path = path;
+        Ok(suggestions)
+    }
+
+    fn save(&self) -> bool {
+        persist::save(&self.path, self)
+    }
+
+    /// Queues a suggestion and persists the queue.
+    pub fn submit(
+        &mut self,
+        lexicon: &str,
+        kind: SuggestionKind,
+        word: String,
+        description: String,
+        user: &User,
+    ) -> bool {
+        self.pending.push(Suggestion {
+            id: self.next_id,
+            lexicon: lexicon.to_string(),
+            kind,
+            word,
+            description,
+            user_name: user.name.clone(),
+            user_id: user.id,
+        });
+        self.next_id += 1;
+        self.save()
+    }
+
+    /// Returns the pending suggestions for a lexicon, oldest first.
+    pub fn pending<'a>(&'a self, lexicon: &'a str) -> impl Iterator<Item = &'a Suggestion> {
+        self.pending
+            .iter()
+            .filter(move |suggestion| suggestion.lexicon == lexicon)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Suggestion> {
+        self.pending.iter().find(|suggestion| suggestion.id == id)
+    }
+
+    /// Removes a reviewed suggestion and persists the queue.
+    pub fn remove(&mut self, id: u64) -> bool {
+        self.pending.retain(|suggestion| suggestion.id != id);
+        self.save()
+    }
+
+    /// Moves the suggestions of a renamed lexicon and persists the queue.
+    pub fn rename(&mut self, lexicon: &str, name: &str) -> bool {
+        for suggestion in &mut self.pending {
+            if suggestion.lexicon == lexicon {
+                suggestion.lexicon = name.to_string();
+            }
+        }
+        self.save()
+    }
+
+    /// Drops the suggestions of a deleted lexicon and persists the queue.
+    pub fn discard(&mut self, lexicon: &str) -> bool {
+        self.pending
+            .retain(|suggestion| suggestion.lexicon != lexicon);
+        self.save()
+    }
+}
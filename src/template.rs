@@ -0,0 +1,47 @@
+/// Replaces every `{key}` in the template with its value.
+///
+/// Unknown placeholders are kept as they are and substituted values are never expanded again.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let key = &rest[1..end];
+        match values.iter().find(|(name, _)| *name == key) {
+            Some((_, value)) => result.push_str(value),
+            None => result.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_placeholders() {
+        let values = [("word", "Apfel"), ("user", "sqyyy")];
+        assert_eq!(
+            render("{word} by {user}, {word}", &values),
+            "Apfel by sqyyy, Apfel"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders() {
+        assert_eq!(render("{unknown} {", &[]), "{unknown} {");
+    }
+
+    #[test]
+    fn does_not_expand_values() {
+        let values = [("word", "{user}"), ("user", "sqyyy")];
+        assert_eq!(render("{word}", &values), "{user}");
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{Capitalization, CharacterClass, ValidationConfig};
+
+/// Applies the configured whitespace handling to a word.
+pub fn normalize(config: &ValidationConfig, word: &str) -> String {
+    let word = if config.trim { word.trim() } else { word };
+    if !config.collapse_whitespace {
+        return word.to_string();
+    }
+    // a trimmed word has no whitespace at its ends, otherwise both ends keep a single space
+    let mut normalized = String::with_capacity(word.len());
+    let mut whitespace = false;
+    for c in word.chars() {
+        if c.is_whitespace() {
+            whitespace = true;
+            continue;
+        }
+        if whitespace {
+            normalized.push(' ');
+        }
+        whitespace = false;
+        normalized.push(c);
+    }
+    if whitespace {
+        normalized.push(' ');
+    }
+    normalized
+}
+
+fn allowed(config: &ValidationConfig, c: char) -> bool {
+    config.allowed_characters.contains(c)
+        || config.allowed.iter().any(|class| match class {
+            CharacterClass::Letter => c.is_alphabetic(),
+            CharacterClass::Digit => c.is_numeric(),
+            CharacterClass::Whitespace => c.is_whitespace(),
+            CharacterClass::Punctuation => c.is_ascii_punctuation(),
+        })
+}
+
+/// Normalizes a new word and checks it against the rules of the lexicon.
+pub fn validate(config: &ValidationConfig, word: &str) -> Result<String, String> {
+    let word = normalize(config, word);
+    if word.is_empty() {
+        return Err("The word is empty.".to_string());
+    }
+    let length = word.chars().count();
+    if length < config.min_length || length > config.max_length {
+        return Err(format!(
+            "The word must be between {} and {} characters long.",
+            config.min_length, config.max_length
+        ));
+    }
+    if !config.allowed.is_empty() || !config.allowed_characters.is_empty() {
+        if let Some(c) = word.chars().find(|c| !allowed(config, *c)) {
+            return Err(format!("The character '{c}' is not allowed in words."));
+        }
+    }
+    let lowercase = word.to_lowercase();
+    if config
+        .reserved
+        .iter()
+        .any(|reserved| reserved.to_lowercase() == lowercase)
+    {
+        return Err(format!("The word '{word}' is reserved."));
+    }
+    let capitalized = match config.capitalization {
+        Capitalization::Any => true,
+        Capitalization::Lowercase => word == lowercase,
+        Capitalization::Uppercase => word == word.to_uppercase(),
+        Capitalization::Capitalized => word
+            .chars()
+            .next()
+            .map(|c| !c.is_lowercase())
+            .unwrap_or(true),
+    };
+    if !capitalized {
+        let rule = match config.capitalization {
+            Capitalization::Lowercase => "written in lowercase",
+            Capitalization::Uppercase => "written in uppercase",
+            _ => "capitalized",
+        };
+        return Err(format!("The word must be {rule}."));
+    }
+    Ok(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_whitespace() {
+        let config = ValidationConfig::default();
+        assert_eq!(normalize(&config, "  big \t  apple\n"), "big apple");
+    }
+
+    #[test]
+    fn collapses_both_ends_without_trimming() {
+        let config = ValidationConfig {
+            trim: false,
+            ..Default::default()
+        };
+        assert_eq!(normalize(&config, "  big   apple  "), " big apple ");
+        let config = ValidationConfig {
+            trim: false,
+            collapse_whitespace: false,
+            ..Default::default()
+        };
+        assert_eq!(normalize(&config, "  big   apple  "), "  big   apple  ");
+    }
+
+    #[test]
+    fn validates_length() {
+        let config = ValidationConfig::default();
+        assert_eq!(validate(&config, " apple "), Ok("apple".to_string()));
+        assert!(validate(&config, "   ").is_err());
+        assert!(validate(&config, "a").is_err());
+        assert!(validate(&config, &"a".repeat(51)).is_err());
+    }
+
+    #[test]
+    fn validates_characters() {
+        let config = ValidationConfig {
+            allowed: vec![CharacterClass::Letter],
+            allowed_characters: "-".to_string(),
+            ..Default::default()
+        };
+        assert!(validate(&config, "well-known").is_ok());
+        assert_eq!(
+            validate(&config, "well known"),
+            Err("The character ' ' is not allowed in words.".to_string())
+        );
+    }
+
+    #[test]
+    fn validates_reserved_words_and_capitalization() {
+        let config = ValidationConfig {
+            reserved: vec!["Help".to_string()],
+            capitalization: Capitalization::Capitalized,
+            ..Default::default()
+        };
+        assert!(validate(&config, "HELP").is_err());
+        assert!(validate(&config, "apple").is_err());
+        assert!(validate(&config, "Apple").is_ok());
+    }
+}